@@ -0,0 +1,89 @@
+// Signs the version manifest Fluster downloads, see "Version manifest" in the README.
+//
+//   bun scripts/sign-manifest.mjs keygen <private-key.pem>
+//   bun scripts/sign-manifest.mjs sign <versions.json> <private-key.pem> <manifest.json> [archives-dir]
+//
+// When an archives directory is given, the size and sha256 of every version
+// are filled in from <archives-dir>/<id>.zip before signing.
+
+import { createHash, createPrivateKey, createPublicKey, generateKeyPairSync, sign } from "node:crypto";
+import { existsSync, readFileSync, statSync, writeFileSync } from "node:fs";
+import { join } from "node:path";
+
+const fail = (message) => {
+  console.error(message);
+  process.exit(1);
+};
+
+const publicKeyHex = (key) =>
+  Buffer.from(createPublicKey(key).export({ format: "jwk" }).x, "base64url").toString("hex");
+
+const keygen = (keyPath) => {
+  if (!keyPath) fail("Usage: sign-manifest.mjs keygen <private-key.pem>");
+  if (existsSync(keyPath)) fail(`${keyPath} already exists, refusing to overwrite it`);
+
+  const { privateKey } = generateKeyPairSync("ed25519");
+
+  writeFileSync(keyPath, privateKey.export({ format: "pem", type: "pkcs8" }), { mode: 0o600 });
+
+  console.log(`Private key written to ${keyPath}, keep it out of the repository.`);
+  console.log(`MANIFEST_PUBLIC_KEY: ${publicKeyHex(privateKey)}`);
+};
+
+const checkVersion = (version) => {
+  for (const field of ["id", "name", "url"]) {
+    if (typeof version[field] !== "string" || version[field].length === 0) {
+      fail(`A version is missing its ${field}`);
+    }
+  }
+
+  if (!Number.isInteger(version.year)) fail(`${version.id} is missing its year`);
+  if (!Number.isInteger(version.size) || version.size <= 0) fail(`${version.id} has no archive size`);
+  if (!/^[0-9a-f]{64}$/i.test(version.sha256 ?? "")) fail(`${version.id} has no archive sha256`);
+};
+
+const signManifest = (versionsPath, keyPath, outputPath, archivesDir) => {
+  if (!versionsPath || !keyPath || !outputPath) {
+    fail("Usage: sign-manifest.mjs sign <versions.json> <private-key.pem> <manifest.json> [archives-dir]");
+  }
+
+  const manifest = JSON.parse(readFileSync(versionsPath, "utf8"));
+
+  if (!Array.isArray(manifest.versions)) fail(`${versionsPath} has no versions list`);
+
+  for (const version of manifest.versions) {
+    if (archivesDir) {
+      const archive = join(archivesDir, `${version.id}.zip`);
+      const content = readFileSync(archive);
+
+      version.size = statSync(archive).size;
+      version.sha256 = createHash("sha256").update(content).digest("hex");
+    }
+
+    checkVersion(version);
+  }
+
+  const privateKey = createPrivateKey(readFileSync(keyPath));
+
+  // the payload is kept as a string, Fluster verifies exactly these bytes
+  const payload = JSON.stringify(manifest);
+  const signature = sign(null, Buffer.from(payload), privateKey).toString("hex");
+
+  writeFileSync(outputPath, JSON.stringify({ payload, signature }, null, 2) + "\n");
+
+  console.log(`Signed ${manifest.versions.length} version(s) into ${outputPath}`);
+  console.log(`Signed with public key ${publicKeyHex(privateKey)}`);
+};
+
+const [command, ...args] = process.argv.slice(2);
+
+switch (command) {
+  case "keygen":
+    keygen(...args);
+    break;
+  case "sign":
+    signManifest(...args);
+    break;
+  default:
+    fail("Usage: sign-manifest.mjs <keygen|sign> ...");
+}
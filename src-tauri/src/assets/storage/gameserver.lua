@@ -3,6 +3,9 @@
 -- file description: An script that allows studio instances to run as a server and allow connection from other LAN clients
 
 local placeID, port = ...
+
+-- point asset requests at the local web server, which may not be on port 80
+pcall(function() game:GetService("ContentProvider"):SetBaseUrl("{{base_url}}/") end)
 local sleepTime = 10
 
 local ns = game:service("NetworkServer")
@@ -4,6 +4,9 @@
 
 local client
 
+-- point asset requests at the local web server, which may not be on port 80
+pcall(function() game:GetService("ContentProvider"):SetBaseUrl("{{base_url}}/") end)
+
 local function showErrorMessage(message)
 	game:SetMessage(message)
 	wait(math.huge)
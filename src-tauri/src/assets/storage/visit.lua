@@ -5,6 +5,9 @@
 local RunService = game:GetService("RunService")
 local NetworkServer = game:GetService("NetworkServer")
 
+-- point asset requests at the local web server, which may not be on port 80
+pcall(function() game:GetService("ContentProvider"):SetBaseUrl("{{base_url}}/") end)
+
 -- handling player added logic
 function onPlayerAdded(player)
     -- new player joined, handle characteradded logic    
@@ -1,11 +1,12 @@
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::{copy, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager, State};
 use zip::ZipArchive;
 
 mod routes;
@@ -13,133 +14,159 @@ mod utils;
 
 use routes::*;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct VersionStats {
-    total_play_time: u64,
-    last_played: u64,
-    is_running: bool,
-    start_time: Option<u64>,
-    size_bytes: u64,
+#[derive(Default)]
+struct InstallJobs {
+    jobs: Mutex<HashMap<String, Arc<AtomicBool>>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct VersionsData {
-    versions: HashMap<String, VersionStats>,
+#[derive(Debug, Serialize)]
+struct InstallProgress<'a> {
+    version: &'a str,
+    phase: &'a str,
+    downloaded: u64,
+    total: Option<u64>,
+    entry: Option<&'a str>,
+    entry_index: usize,
+    entry_count: usize,
 }
 
-impl Default for VersionsData {
-    fn default() -> Self {
-        VersionsData {
-            versions: HashMap::new(),
-        }
+const INSTALL_PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Whether another progress event is due. Events are sent at most every
+/// `INSTALL_PROGRESS_INTERVAL`, except the `finished` one which always goes
+/// out so the setup screen ends up on the real state.
+fn progress_due(last_emit: &mut Option<std::time::Instant>, finished: bool) -> bool {
+    if !finished && last_emit.is_some_and(|t| t.elapsed() < INSTALL_PROGRESS_INTERVAL) {
+        return false;
     }
+
+    *last_emit = Some(std::time::Instant::now());
+    true
 }
 
-const HOSTS_ENTRIES: &str = "\
-# Fluster Local Domain Entries
-127.0.0.1 fluster.is
-127.0.0.1 www.fluster.is
-";
+/// How long hosting waits for the assets of a place before starting anyway,
+/// whatever is left gets fetched when the server asks for it.
+const PREFETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
 
-#[tauri::command]
-fn setup_hosts_file() -> Result<String, String> {
-    #[cfg(target_os = "windows")]
-    {
-        use std::os::windows::ffi::OsStrExt;
-        use winapi::um::{shellapi::ShellExecuteW, winuser::SW_HIDE};
+fn emit_install_progress(app: &AppHandle, progress: &InstallProgress) {
+    match serde_json::to_string(progress) {
+        Ok(payload) => app
+            .emit("install_progress", payload)
+            .unwrap_or_else(|e| eprintln!("Failed to emit install progress event: {}", e)),
+        Err(e) => eprintln!("Failed to serialize install progress: {}", e),
+    }
+}
 
-        let hosts_path = r"C:\Windows\System32\drivers\etc\hosts";
+/// Reruns Fluster as administrator with `flag` so it can edit the hosts file.
+#[cfg(target_os = "windows")]
+fn run_elevated(flag: &str) -> bool {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::{shellapi::ShellExecuteW, winuser::SW_HIDE};
 
-        if !std::path::Path::new(hosts_path).exists() {
-            return Err(format!("Hosts file not found at {}", hosts_path));
-        }
+    fn to_wide(s: &str) -> Vec<u16> {
+        OsStr::new(s).encode_wide().chain(Some(0)).collect()
+    }
 
-        let mut file = match std::fs::OpenOptions::new().read(true).open(hosts_path) {
-            Ok(file) => file,
-            Err(e) => return match e.kind() {
-                std::io::ErrorKind::PermissionDenied => Err(
-                    "Permission denied while opening the hosts file. Please run Fluster as administrator once."
-                        .to_string(),
-                ),
-                std::io::ErrorKind::NotFound => {
-                    Err(format!("Hosts file not found at {}", hosts_path))
-                }
-                _ => Err(format!("Failed to open hosts file: {}", e)),
-            },
-        };
+    let Ok(exe) = std::env::current_exe() else {
+        return false;
+    };
+
+    let exe_w = to_wide(&exe.to_string_lossy());
+    let verb = to_wide("runas");
+    let args = to_wide(flag);
+
+    let result = unsafe {
+        ShellExecuteW(
+            std::ptr::null_mut(),
+            verb.as_ptr(),
+            exe_w.as_ptr(),
+            args.as_ptr(),
+            std::ptr::null(),
+            SW_HIDE,
+        )
+    };
 
-        if file.metadata().is_ok() {
-            let mut contents = String::new();
+    (result as isize) > 32
+}
 
-            file.read_to_string(&mut contents)
-                .map_err(|e| format!("Failed to read hosts file: {}", e))?;
+#[tauri::command]
+fn setup_hosts_file() -> Result<String, String> {
+    if utils::server::load_server_config().domain_mode == utils::server::DomainMode::Loopback {
+        return Ok(
+            "The hosts file is not needed while clients connect over the loopback address."
+                .to_string(),
+        );
+    }
 
-            if contents.contains(HOSTS_ENTRIES) {
-                return Ok("Hosts file already contains Fluster entries".to_string());
-            }
-        }
+    let hosts_path = utils::hosts::hosts_path();
 
-        fn to_wide(s: &str) -> Vec<u16> {
-            OsStr::new(s).encode_wide().chain(Some(0)).collect()
-        }
+    if !Path::new(hosts_path).exists() {
+        return Err(format!("Hosts file not found at {}", hosts_path));
+    }
 
-        let exe = std::env::current_exe().map_err(|e| e.to_string())?;
-        let exe_w = to_wide(&exe.to_string_lossy());
-        let verb = to_wide("runas");
-        let args = to_wide("--write-hosts");
-
-        let result = unsafe {
-            ShellExecuteW(
-                std::ptr::null_mut(),
-                verb.as_ptr(),
-                exe_w.as_ptr(),
-                args.as_ptr(),
-                std::ptr::null(),
-                SW_HIDE,
-            )
-        };
+    if utils::hosts::hosts_file_has_entries()? {
+        return Ok("Hosts file already contains Fluster entries".to_string());
+    }
 
-        if (result as isize) <= 32 {
-            Err("We couldn't add the local Fluster Domains onto your machine, did you accept the UAC prompt?".into())
-        } else {
+    #[cfg(target_os = "windows")]
+    {
+        if run_elevated("--write-hosts") {
             Ok("Setup the local Fluster Domains onto the machine!".into())
+        } else {
+            Err("We couldn't add the local Fluster Domains onto your machine, did you accept the UAC prompt?".into())
         }
     }
     #[cfg(not(target_os = "windows"))]
     {
-        let hosts_path = "/etc/hosts";
+        utils::hosts::write_hosts_file()?;
 
-        if !std::path::Path::new(hosts_path).exists() {
-            return Err(format!("Hosts file not found at {}", hosts_path));
-        }
+        Ok("Hosts file updated successfully.".to_string())
+    }
+}
 
-        let contents = std::fs::read_to_string(hosts_path)
-            .map_err(|e| format!("Failed to read hosts file: {}", e))?;
+#[tauri::command]
+fn uninstall_hosts_entries() -> Result<String, String> {
+    let hosts_path = utils::hosts::hosts_path();
 
-        if contents.contains(HOSTS_ENTRIES) {
-            return Ok("Hosts file already contains Fluster entries".to_string());
-        }
+    if !Path::new(hosts_path).exists() {
+        return Err(format!("Hosts file not found at {}", hosts_path));
+    }
 
-        let mut file = match std::fs::OpenOptions::new().append(true).open(hosts_path) {
-            Ok(file) => file,
-            Err(e) => {
-                return match e.kind() {
-                    std::io::ErrorKind::PermissionDenied => Err(
-                        "Permission denied to write to hosts file. Please run with sudo."
-                            .to_string(),
-                    ),
-                    _ => Err(format!("Failed to open hosts file for writing: {}", e)),
-                }
-            }
-        };
+    let contents = fs::read_to_string(hosts_path)
+        .map_err(|e| format!("Failed to read hosts file: {}", e))?;
 
-        writeln!(file, "\n{}", HOSTS_ENTRIES)
-            .map_err(|e| format!("Failed to write to hosts file: {}", e))?;
+    if !utils::hosts::contains_entries(&contents) {
+        return Ok("Hosts file has no Fluster entries".to_string());
+    }
 
-        Ok("Hosts file updated successfully.".to_string())
+    #[cfg(target_os = "windows")]
+    {
+        if run_elevated("--remove-hosts") {
+            Ok("Removed the local Fluster Domains from the machine.".into())
+        } else {
+            Err("We couldn't remove the local Fluster Domains from your machine, did you accept the UAC prompt?".into())
+        }
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        utils::hosts::clean_hosts_file()?;
+
+        Ok("Removed the Fluster entries from the hosts file.".to_string())
     }
 }
 
+#[tauri::command]
+async fn check_domain_resolution() -> Result<String, String> {
+    // resolving can take a while, keep it off the main thread
+    let resolution = tauri::async_runtime::spawn_blocking(utils::hosts::check_resolution)
+        .await
+        .map_err(|e| format!("Failed to check the domain resolution: {}", e))?;
+
+    serde_json::to_string(&resolution)
+        .map_err(|e| format!("Failed to serialize the domain resolution: {}", e))
+}
+
 #[tauri::command]
 fn start_listening(app: AppHandle) -> Result<(), String> {
     let client_result = utils::network::lan_discovery::start_discovery()
@@ -148,6 +175,13 @@ fn start_listening(app: AppHandle) -> Result<(), String> {
     std::thread::spawn(move || {
         if let Ok(client) = client_result {
             while let Ok((addr, server_info)) = client.receiver.recv() {
+                if let Some(asset_port) = server_info.asset_port {
+                    utils::lan_assets::register_source(std::net::SocketAddr::new(
+                        addr.ip(),
+                        asset_port,
+                    ));
+                }
+
                 let discovery_info = serde_json::json!({
                     "host": addr.ip().to_string(),
                     "port": addr.port(),
@@ -165,15 +199,76 @@ fn start_listening(app: AppHandle) -> Result<(), String> {
     Ok(())
 }
 
-#[tauri::command]
-fn start_server(version: &str, file_path: &str) -> Result<(), String> {
-    let port = rand::random::<u16>() % 65535 + 1;
+#[derive(Debug, Serialize)]
+struct PrefetchProgress<'a> {
+    place_file: &'a str,
+    completed: usize,
+    total: usize,
+    failed: usize,
+}
 
-    let data = match utils::appdata::return_versions() {
-        Ok(path) => path,
-        Err(_) => return Err("Failed to get the versions directory.".to_string()),
+/// Warms the asset cache with everything the place references, so neither the
+/// server nor the players joining it wait on first fetches.
+async fn prefetch_place_assets(app: &AppHandle, file_path: &str) {
+    let assets = match utils::places::scan_place_file(Path::new(file_path)) {
+        Ok(assets) => assets,
+        Err(e) => {
+            eprintln!("Skipping the asset prefetch: {}", e);
+            return;
+        }
     };
 
+    routes::asset::prefetch_assets(assets, |completed, total, failed| {
+        let progress = PrefetchProgress {
+            place_file: file_path,
+            completed,
+            total,
+            failed,
+        };
+
+        match serde_json::to_string(&progress) {
+            Ok(payload) => app
+                .emit("prefetch_progress", payload)
+                .unwrap_or_else(|e| eprintln!("Failed to emit prefetch progress event: {}", e)),
+            Err(e) => eprintln!("Failed to serialize prefetch progress: {}", e),
+        }
+    })
+    .await;
+}
+
+/// Starts the server LAN peers fetch assets from while this instance hosts.
+async fn start_lan_asset_server(port: u16) -> Result<rocket::Shutdown, String> {
+    let config = rocket::Config::figment()
+        .merge(("port", port))
+        .merge(("address", "0.0.0.0"))
+        .merge(("shutdown.timeout", 0))
+        .merge(("workers", 2));
+
+    let rocket = rocket::custom(config)
+        .mount("/", rocket::routes![asset::lan])
+        .ignite()
+        .await
+        .map_err(|e| format!("Failed to start the LAN asset server: {}", e))?;
+
+    let shutdown = rocket.shutdown();
+
+    tokio::spawn(async move {
+        if let Err(e) = rocket.launch().await {
+            eprintln!("LAN asset server error: {}", e);
+        }
+    });
+
+    Ok(shutdown)
+}
+
+#[tauri::command]
+async fn start_server(
+    app: AppHandle,
+    version: &str,
+    file_path: &str,
+) -> Result<(), String> {
+    let port = rand::random::<u16>() % 65535 + 1;
+
     if !utils::client::is_client_installed(version) {
         return Err("Client is not installed.".to_string());
     }
@@ -184,88 +279,131 @@ fn start_server(version: &str, file_path: &str) -> Result<(), String> {
         return Err("Game file does not exist.".to_string());
     }
 
-    let server_discovery_message = utils::network::lan_discovery::start_server(port, version)
-        .map_err(|e| format!("Failed to start the server: {}", e))?;
+    utils::client::prepare_base_url(version)?;
 
-    let mut server_launch = std::process::Command::new(data.join(version).join("Roblox.exe"))
-        .arg(file_path)
-        .arg("-no3d")
-        .arg("-script")
-        .arg(format!(
-            "loadfile('http://www.fluster.is/game/gameserver.ashx')(0, {})",
-            port.to_string()
-        ))
-        .spawn()
-        .map_err(|_| "Failed to launch the server.".to_string())?;
+    if tokio::time::timeout(PREFETCH_TIMEOUT, prefetch_place_assets(&app, file_path))
+        .await
+        .is_err()
+    {
+        eprintln!("The asset prefetch for {} is taking too long, hosting without it", file_path);
+    }
 
-    std::thread::spawn(move || {
-        let _ = server_launch.wait();
-        server_discovery_message.stop();
-    });
+    let asset_port = utils::lan_assets::reserve_port()
+        .map_err(|e| eprintln!("Failed to reserve a port for the LAN asset server: {}", e))
+        .ok();
+
+    let mut args = vec![
+        file_path.to_string(),
+        "-no3d".to_string(),
+        "-script".to_string(),
+        format!(
+            "loadfile('{}/game/gameserver.ashx')(0, {})",
+            utils::server::base_url(),
+            port
+        ),
+    ];
+    args.extend(utils::manifest::cached_launch_args(version));
+
+    let mut command = utils::client::client_command(version, &args)?;
+
+    let server_discovery_message =
+        utils::network::lan_discovery::start_server(port, version, asset_port)
+            .map_err(|e| format!("Failed to start the server: {}", e))?;
+
+    let server_launch = match command.spawn() {
+        Ok(server_launch) => server_launch,
+        Err(_) => {
+            // nothing to advertise without the server process, dropping the
+            // handle ends the broadcast without waiting out its sleep
+            drop(server_discovery_message);
+            return Err("Failed to launch the server.".to_string());
+        }
+    };
+
+    utils::overrides::set_active_version(version);
+
+    let mut lan_asset_server = None;
+
+    if let Some(asset_port) = asset_port {
+        match start_lan_asset_server(asset_port).await {
+            Ok(shutdown) => {
+                utils::lan_assets::set_serving_port(Some(asset_port));
+                lan_asset_server = Some(shutdown);
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    utils::supervisor::supervise(
+        server_launch,
+        version,
+        utils::supervisor::LaunchKind::Server,
+        utils::supervisor::LaunchDetails {
+            place_file: Some(file_path.to_string()),
+            server_address: None,
+        },
+        move |exit| {
+            server_discovery_message.stop();
+
+            if let Some(shutdown) = lan_asset_server {
+                utils::lan_assets::set_serving_port(None);
+                shutdown.notify();
+            }
+
+            record_session(&exit);
+            emit_launch_exit(&app, &exit);
+        },
+    );
 
     Ok(())
 }
 
 #[tauri::command]
 fn launch_server_connection(
+    app: AppHandle,
     version: &str,
     server_ip: &str,
     server_port: u16,
     user_id: usize,
 ) -> Result<bool, String> {
-    let data = match utils::appdata::return_versions() {
-        Ok(path) => path,
-        Err(_) => return Err("Failed to get the versions directory.".to_string()),
-    };
-
     if !utils::client::is_client_installed(version) {
         return Err("Client is not installed.".to_string());
     }
 
-    let client_path = data.join(version).join("Roblox.exe");
+    utils::overrides::set_active_version(version);
+    utils::client::prepare_base_url(version)?;
 
-    let result = std::process::Command::new(client_path)
-        .arg("-script")
-        .arg(format!(
-            "http://www.fluster.is/game/join.ashx?UserID={}&serverPort={}&serverIP={}",
-            user_id, server_port, server_ip
-        ))
-        .spawn();
+    let mut args = vec![
+        "-script".to_string(),
+        format!(
+            "{}/game/join.ashx?UserID={}&serverPort={}&serverIP={}",
+            utils::server::base_url(),
+            user_id,
+            server_port,
+            server_ip
+        ),
+    ];
+    args.extend(utils::manifest::cached_launch_args(version));
+
+    let result = utils::client::client_command(version, &args)?.spawn();
 
     match result {
-        Ok(_) => {
-            let version_owned = version.to_string();
-            update_version_stats(&version_owned, true);
-
-            std::thread::spawn(move || {
-                std::thread::sleep(std::time::Duration::from_secs(2));
-
-                loop {
-                    std::thread::sleep(std::time::Duration::from_secs(5));
-
-                    let process_name = if cfg!(target_os = "windows") {
-                        "Roblox.exe"
-                    } else {
-                        "Roblox"
-                    };
-
-                    let output = std::process::Command::new("ps").arg("aux").output();
-
-                    match output {
-                        Ok(output) => {
-                            let processes = String::from_utf8_lossy(&output.stdout);
-                            if !processes.contains(process_name) {
-                                update_version_stats(&version_owned, false);
-                                break;
-                            }
-                        }
-                        Err(_) => {
-                            update_version_stats(&version_owned, false);
-                            break;
-                        }
-                    }
-                }
-            });
+        Ok(child) => {
+            if !utils::supervisor::is_playing(version) {
+                app.state::<utils::stats::StatsStore>().start_session(version);
+            }
+
+            utils::supervisor::supervise(
+                child,
+                version,
+                utils::supervisor::LaunchKind::Join,
+                utils::supervisor::LaunchDetails {
+                    place_file: None,
+                    server_address: Some(format!("{}:{}", server_ip, server_port)),
+                },
+                move |exit| finish_launch(&app, &exit),
+            );
+
             Ok(true)
         }
         Err(_) => Err("Failed to launch the client.".to_string()),
@@ -302,42 +440,49 @@ fn is_version_installed(version: &str) -> bool {
 }
 
 #[tauri::command]
-fn launch_client(version: &str) -> Result<bool, String> {
-    let result = utils::client::launch_client(version);
-    if result.is_ok() {
-        let version_owned = version.to_string();
-        update_version_stats(&version_owned, true);
+fn launch_client(app: AppHandle, version: &str) -> Result<bool, String> {
+    let child = utils::client::launch_client(version)?;
 
-        std::thread::spawn(move || {
-            std::thread::sleep(std::time::Duration::from_secs(2));
-            loop {
-                std::thread::sleep(std::time::Duration::from_secs(5));
+    // a second client of the same version keeps the running session going
+    if !utils::supervisor::is_playing(version) {
+        app.state::<utils::stats::StatsStore>().start_session(version);
+    }
 
-                let process_name = if cfg!(target_os = "windows") {
-                    "Roblox.exe"
-                } else {
-                    "Roblox"
-                };
+    utils::supervisor::supervise(
+        child,
+        version,
+        utils::supervisor::LaunchKind::Client,
+        utils::supervisor::LaunchDetails::default(),
+        move |exit| finish_launch(&app, &exit),
+    );
 
-                let output = std::process::Command::new("ps").arg("aux").output();
+    Ok(true)
+}
 
-                match output {
-                    Ok(output) => {
-                        let processes = String::from_utf8_lossy(&output.stdout);
-                        if !processes.contains(process_name) {
-                            update_version_stats(&version_owned, false);
-                            break;
-                        }
-                    }
-                    Err(_) => {
-                        update_version_stats(&version_owned, false);
-                        break;
-                    }
-                }
-            }
-        });
+fn emit_launch_exit(app: &AppHandle, exit: &utils::supervisor::LaunchExit) {
+    match serde_json::to_string(exit) {
+        Ok(payload) => app
+            .emit("client_exited", payload)
+            .unwrap_or_else(|e| eprintln!("Failed to emit client exit event: {}", e)),
+        Err(e) => eprintln!("Failed to serialize the client exit: {}", e),
     }
-    result
+}
+
+fn record_session(exit: &utils::supervisor::LaunchExit) {
+    utils::sessions::record_session(&exit.into())
+        .unwrap_or_else(|e| eprintln!("{}", e));
+}
+
+/// Stops counting play time once the last client of the version is gone.
+fn finish_launch(app: &AppHandle, exit: &utils::supervisor::LaunchExit) {
+    record_session(exit);
+
+    if !utils::supervisor::is_playing(&exit.launch.version) {
+        app.state::<utils::stats::StatsStore>()
+            .end_session(&exit.launch.version);
+    }
+
+    emit_launch_exit(app, exit);
 }
 
 #[tauri::command]
@@ -356,18 +501,71 @@ fn fluster_setup() -> Result<bool, String> {
     return Ok(true);
 }
 
+fn begin_install_job(jobs: &InstallJobs, version: &str) -> Result<Arc<AtomicBool>, String> {
+    let mut jobs = jobs.jobs.lock().unwrap();
+
+    if jobs.contains_key(version) {
+        return Err(format!("{} is already being installed", version));
+    }
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    jobs.insert(version.to_string(), cancel.clone());
+
+    Ok(cancel)
+}
+
+fn discard_version_archive(version: &str) {
+    if let Ok(downloads) = utils::appdata::return_downloads() {
+        let dest_zip = downloads.join(format!("{}.zip", version));
+        let _ = fs::remove_file(utils::download::part_path(&dest_zip));
+        let _ = fs::remove_file(dest_zip);
+    }
+}
+
 #[tauri::command]
-async fn install_client(version: &str) -> Result<bool, String> {
-    println!("starting installation for version: {}", version);
+async fn install_client(
+    app: AppHandle,
+    jobs: State<'_, InstallJobs>,
+    version: &str,
+) -> Result<bool, String> {
+    let cancel = begin_install_job(&jobs, version)?;
 
-    let versions = match utils::appdata::return_versions() {
-        Ok(path) => {
-            println!("versions directory: {:?}", path);
-            path
+    let result = install_version(&app, version, &cancel).await;
+
+    jobs.jobs.lock().unwrap().remove(version);
+
+    // a cancel arriving after the install finished changes nothing
+    if result.is_err() && cancel.load(Ordering::Relaxed) {
+        println!("installation of {} was cancelled", version);
+
+        // a cancelled install should not leave its download behind either
+        discard_version_archive(version);
+
+        return Err(format!("Installation of {} was cancelled", version));
+    }
+
+    result
+}
+
+#[tauri::command]
+fn cancel_install(jobs: State<'_, InstallJobs>, version: &str) -> bool {
+    match jobs.jobs.lock().unwrap().get(version) {
+        Some(cancel) => {
+            cancel.store(true, Ordering::Relaxed);
+            true
         }
-        Err(e) => return Err(format!("Failed to get the versions directory: {}", e)),
-    };
+        None => false,
+    }
+}
 
+/// Makes sure the archive for `version` is in the downloads directory and
+/// matches the version manifest, downloading it when it isn't there yet.
+async fn download_version_archive(
+    app: &AppHandle,
+    version: &str,
+    manifest_version: &utils::manifest::ManifestVersion,
+    cancel: &AtomicBool,
+) -> Result<std::path::PathBuf, String> {
     let downloads = match utils::appdata::return_downloads() {
         Ok(path) => {
             println!("downloads directory: {:?}", path);
@@ -376,189 +574,652 @@ async fn install_client(version: &str) -> Result<bool, String> {
         Err(e) => return Err(format!("Failed to get the downloads directory: {}", e)),
     };
 
-    if utils::client::is_client_installed(version) {
-        println!("version {} is already installed", version);
-        return Ok(true);
+    let dest_zip = downloads.join(format!("{}.zip", version));
+    println!("download destination: {:?}", dest_zip);
+
+    if dest_zip.exists() {
+        println!("using previously downloaded archive");
+
+        if verify_version_archive(app, version, manifest_version, &dest_zip)
+            .await
+            .is_ok()
+        {
+            return Ok(dest_zip);
+        }
     }
 
-    let url = format!("https://cdn.simuldev.com/{}.zip", version);
-    println!("downloading from url: {}", url);
-    let dest_zip = downloads.join(version);
-    println!("download destination: {:?}", dest_zip);
+    let mut errors = Vec::new();
 
-    let response = reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Failed to contact with the Fluster Storage: {}", e))?;
+    for (mirror, url) in utils::mirrors::candidate_urls(version, &manifest_version.url) {
+        println!("downloading from url: {}", url);
 
-    println!("download response status: {}", response.status());
+        let mut last_emit: Option<std::time::Instant> = None;
 
-    if response.status() == reqwest::StatusCode::NOT_FOUND {
-        return Err(format!(
-            "{} was not available on the Fluster Storage",
-            version
-        ));
+        let fetched = utils::download::download(&url, &dest_zip, cancel, |downloaded, total| {
+            if !progress_due(&mut last_emit, total == Some(downloaded)) {
+                return;
+            }
+
+            emit_install_progress(
+                app,
+                &InstallProgress {
+                    version,
+                    phase: "downloading",
+                    downloaded,
+                    total,
+                    entry: None,
+                    entry_index: 0,
+                    entry_count: 0,
+                },
+            );
+        })
+        .await;
+
+        let downloaded = match fetched {
+            Ok(_) => verify_version_archive(app, version, manifest_version, &dest_zip).await,
+            Err(e) => Err(e),
+        };
+
+        match downloaded {
+            Ok(()) => {
+                utils::mirrors::record_success(&mirror);
+                println!("successfully wrote zip file to: {:?}", dest_zip);
+                return Ok(dest_zip);
+            }
+            Err(e) if cancel.load(Ordering::Relaxed) => return Err(e),
+            Err(e) => {
+                println!("download from {} failed: {}", mirror, e);
+                utils::mirrors::record_failure(&mirror, &e);
+                errors.push(format!("{}: {}", mirror, e));
+            }
+        }
     }
 
-    if response.status() != reqwest::StatusCode::OK || response.content_length().is_none() {
-        return Err(format!(
-            "Failed to download {} for error code {}",
+    Err(format!(
+        "Failed to download {} from any mirror ({})",
+        version,
+        errors.join("; ")
+    ))
+}
+
+/// Checks a downloaded archive against the version manifest, removing it when
+/// it doesn't match so it is fetched again.
+async fn verify_version_archive(
+    app: &AppHandle,
+    version: &str,
+    manifest_version: &utils::manifest::ManifestVersion,
+    dest_zip: &Path,
+) -> Result<(), String> {
+    let archive_size = fs::metadata(dest_zip).map(|m| m.len()).unwrap_or(0);
+
+    emit_install_progress(
+        app,
+        &InstallProgress {
             version,
-            response.status()
-        ));
-    }
+            phase: "verifying",
+            downloaded: archive_size,
+            total: Some(archive_size),
+            entry: None,
+            entry_index: 0,
+            entry_count: 0,
+        },
+    );
 
-    println!("content length: {:?}", response.content_length());
+    // the builtin versions have nothing to check against
+    if !manifest_version.signed {
+        return Ok(());
+    }
 
-    let content = response
-        .bytes()
+    // hashing the archive takes a while, keep it off the runtime the web server runs on
+    let path = dest_zip.to_path_buf();
+    let checksum = tauri::async_runtime::spawn_blocking(move || utils::manifest::sha256_file(&path))
         .await
-        .map_err(|e| format!("Failed to read the response body: {}", e))?;
+        .map_err(|e| format!("Failed to verify the archive: {}", e))??;
 
-    println!("downloaded content size: {} bytes", content.len());
-
-    File::create(&dest_zip)
-        .map_err(|e| format!("Failed to create the destination file: {}", e))?
-        .write_all(&content)
-        .map_err(|e| format!("Failed to write the version data to the file: {}", e))?;
+    if archive_size != manifest_version.size
+        || !checksum.eq_ignore_ascii_case(&manifest_version.sha256)
+    {
+        let _ = fs::remove_file(dest_zip);
+        return Err(format!(
+            "The downloaded archive for {} does not match the version manifest",
+            version
+        ));
+    }
 
-    println!("successfully wrote zip file to: {:?}", dest_zip);
+    Ok(())
+}
 
-    let file = File::open(&dest_zip).map_err(|e| format!("Failed to open the zip file: {}", e))?;
+fn open_version_archive(dest_zip: &Path) -> Result<ZipArchive<File>, String> {
+    let file = File::open(dest_zip).map_err(|e| format!("Failed to open the zip file: {}", e))?;
 
-    let mut archive =
-        ZipArchive::new(file).map_err(|e| format!("Failed to create the zip archive: {}", e))?;
+    match ZipArchive::new(file) {
+        Ok(archive) => Ok(archive),
+        Err(e) => {
+            // a broken archive would otherwise be reused on every retry
+            let _ = fs::remove_file(dest_zip);
+            Err(format!("Failed to create the zip archive: {}", e))
+        }
+    }
+}
 
-    println!(
-        "successfully opened zip archive with {} files",
-        archive.len()
-    );
+async fn install_version(
+    app: &AppHandle,
+    version: &str,
+    cancel: &Arc<AtomicBool>,
+) -> Result<bool, String> {
+    println!("starting installation for version: {}", version);
 
-    let version_path = versions.join(version);
-    println!("target installation path: {:?}", version_path);
+    let versions = match utils::appdata::return_versions() {
+        Ok(path) => {
+            println!("versions directory: {:?}", path);
+            path
+        }
+        Err(e) => return Err(format!("Failed to get the versions directory: {}", e)),
+    };
 
-    if !version_path.exists() {
-        println!("creating version directory");
-        fs::create_dir_all(&version_path)
-            .map_err(|e| format!("Failed to create version directory: {}", e))?;
+    if utils::client::is_client_installed(version) {
+        println!("version {} is already installed", version);
+        return Ok(true);
     }
 
-    for i in 0..archive.len() {
-        let mut entry = archive
-            .by_index(i)
-            .map_err(|e| format!("Failed to read the zip entry: {}", e))?;
+    let manifest_version = utils::manifest::find_version(version).await?;
 
-        let out_path = version_path.join(entry.name());
-        println!("extracting: {} to {:?}", entry.name(), out_path);
+    let dest_zip = download_version_archive(app, version, &manifest_version, cancel).await?;
+    let archive_size = fs::metadata(&dest_zip).map(|m| m.len()).unwrap_or(manifest_version.size);
 
-        if entry.is_dir() {
-            fs::create_dir_all(&out_path)
-                .map_err(|e| format!("Failed to create the directory: {}", e))?;
-        } else {
-            if let Some(p) = out_path.parent() {
-                if !p.exists() {
-                    println!("creating parent directory: {:?}", p);
-                    fs::create_dir_all(p)
-                        .map_err(|e| format!("Failed to create the parent directory: {}", e))?;
-                }
-            }
+    let mut archive = open_version_archive(&dest_zip)?;
 
-            let mut outfile =
-                File::create(&out_path).map_err(|e| format!("Failed to create the file: {}", e))?;
+    let entry_count = archive.len();
+    println!("successfully opened zip archive with {} files", entry_count);
 
-            copy(&mut entry, &mut outfile)
-                .map_err(|e| format!("Failed to write the file: {}", e))?;
+    let version_path = versions.join(version);
+    let staging_path = utils::install::staging_path(&versions, version);
+    println!("target installation path: {:?}", version_path);
+    println!("staging installation path: {:?}", staging_path);
 
-            println!("successfully extracted: {:?}", out_path);
-        }
+    if staging_path.exists() {
+        fs::remove_dir_all(&staging_path)
+            .map_err(|e| format!("Failed to clean up a previous installation attempt: {}", e))?;
     }
 
-    let size = calculate_dir_size(&version_path)
-        .map_err(|e| format!("Failed to calculate directory size: {}", e))?;
+    // unpacking takes a while, keep it off the runtime the web server runs on
+    let extracted = {
+        let app = app.clone();
+        let version = version.to_string();
+        let staging_path = staging_path.clone();
+        let cancel = cancel.clone();
+
+        tauri::async_runtime::spawn_blocking(move || {
+            let mut last_emit = None;
+
+            utils::install::extract_archive(
+                &mut archive,
+                &staging_path,
+                &cancel,
+                |name, index, count| {
+                    // archives hold thousands of entries, don't send an event for each
+                    if !progress_due(&mut last_emit, index == count) {
+                        return;
+                    }
 
-    println!("installation size: {} bytes", size);
+                    emit_install_progress(
+                        &app,
+                        &InstallProgress {
+                            version: &version,
+                            phase: "extracting",
+                            downloaded: archive_size,
+                            total: Some(archive_size),
+                            entry: Some(name),
+                            entry_index: index,
+                            entry_count: count,
+                        },
+                    );
+                },
+            )
+        })
+        .await
+        .map_err(|e| format!("Failed to extract the archive: {}", e))
+        .and_then(|extracted| extracted)
+    };
 
-    let mut data = load_versions_data();
-    let stats = data
-        .versions
-        .entry(version.to_string())
-        .or_insert(VersionStats {
-            total_play_time: 0,
-            last_played: 0,
-            is_running: false,
-            start_time: None,
-            size_bytes: size,
-        });
-    stats.size_bytes = size;
-    save_versions_data(&data);
+    if let Err(e) = extracted {
+        let _ = fs::remove_dir_all(&staging_path);
+        return Err(format!("Failed to extract {}: {}", version, e));
+    }
 
-    println!("updated version stats");
+    emit_install_progress(
+        app,
+        &InstallProgress {
+            version,
+            phase: "finalizing",
+            downloaded: archive_size,
+            total: Some(archive_size),
+            entry: None,
+            entry_index: entry_count,
+            entry_count,
+        },
+    );
+
+    let installed = finalize_install(app, version, &versions, &staging_path, false).await?;
 
     if let Err(e) = fs::remove_file(dest_zip) {
         println!("Warning: failed to remove temporary zip file: {}", e);
     }
     println!("cleaned up zip file");
 
-    let is_installed = utils::client::is_client_installed(version);
-    println!("final installation check: {}", is_installed);
+    Ok(installed)
+}
 
-    if is_installed {
-        println!("installation completed successfully");
-        Ok(true)
-    } else {
-        if let Ok(entries) = fs::read_dir(&version_path) {
-            println!("contents of {:?}:", version_path);
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    println!("  {:?}", entry.path());
-                }
+/// Moves a populated staging directory into `versions/` and records its file
+/// list and size, shared by everything that adds a version to the device.
+async fn finalize_install(
+    app: &AppHandle,
+    version: &str,
+    versions: &Path,
+    staging_path: &Path,
+    unverified: bool,
+) -> Result<bool, String> {
+    let app = app.clone();
+    let version = version.to_string();
+    let versions = versions.to_path_buf();
+    let staging_path = staging_path.to_path_buf();
+
+    // every installed file gets hashed, keep it off the runtime the web server runs on
+    tauri::async_runtime::spawn_blocking(move || {
+        commit_install(&app, &version, &versions, &staging_path, unverified)
+    })
+    .await
+    .map_err(|e| format!("Failed to finish installing: {}", e))?
+}
+
+fn commit_install(
+    app: &AppHandle,
+    version: &str,
+    versions: &Path,
+    staging_path: &Path,
+    unverified: bool,
+) -> Result<bool, String> {
+    let version_path = versions.join(version);
+
+    if !utils::install::has_client_executable(staging_path) {
+        if let Ok(entries) = fs::read_dir(staging_path) {
+            println!("contents of {:?}:", staging_path);
+            for entry in entries.flatten() {
+                println!("  {:?}", entry.path());
             }
         }
-        Err("Installation failed: Client files not found after installation".to_string())
+
+        let _ = fs::remove_dir_all(staging_path);
+        return Err("Installation failed: Client files not found after installation".to_string());
     }
-}
+
+    let files = match utils::integrity::build_file_records(staging_path) {
+        Ok(files) => files,
+        Err(e) => {
+            let _ = fs::remove_dir_all(staging_path);
+            return Err(e);
+        }
+    };
+
+    if let Err(e) = utils::install::commit_staging(staging_path, &version_path) {
+        let _ = fs::remove_dir_all(staging_path);
+        return Err(e);
+    }
+
+    utils::integrity::save_file_manifest(
+        &utils::integrity::file_manifest_path(versions, version),
+        &utils::integrity::FileManifest {
+            version: version.to_string(),
+            unverified,
+            files,
+        },
+    )?;
+
+    let size = calculate_dir_size(&version_path)
+        .map_err(|e| format!("Failed to calculate directory size: {}", e))?;
+
+    println!("installation size: {} bytes", size);
+
+    app.state::<utils::stats::StatsStore>().set_size(version, size);
+
+    println!("updated version stats");
+
+    let is_installed = utils::client::is_client_installed(version);
+    println!("final installation check: {}", is_installed);
+
+    if is_installed {
+        println!("installation completed successfully");
+        Ok(true)
+    } else {
+        Err("Installation failed: Client files not found after installation".to_string())
+    }
+}
 
 #[tauri::command]
-fn uninstall_client(version: &str) -> Result<String, String> {
+async fn import_client(
+    app: AppHandle,
+    jobs: State<'_, InstallJobs>,
+    version: &str,
+    path: &str,
+    allow_unverified: bool,
+) -> Result<bool, String> {
+    // the only version name typed in by the user
+    utils::appdata::validate_version(version)?;
+
+    let cancel = begin_install_job(&jobs, version)?;
+
+    let result = import_version(&app, version, Path::new(path), allow_unverified, &cancel).await;
+
+    jobs.jobs.lock().unwrap().remove(version);
+
+    // a cancel arriving after the import finished changes nothing
+    if result.is_err() && cancel.load(Ordering::Relaxed) {
+        return Err(format!("Import of {} was cancelled", version));
+    }
+
+    result
+}
+
+async fn import_version(
+    app: &AppHandle,
+    version: &str,
+    source: &Path,
+    allow_unverified: bool,
+    cancel: &Arc<AtomicBool>,
+) -> Result<bool, String> {
+    println!("importing version {} from {:?}", version, source);
+
     let versions = match utils::appdata::return_versions() {
         Ok(path) => path,
         Err(e) => return Err(format!("Failed to get the versions directory: {}", e)),
     };
 
-    let version_path = versions.join(version);
+    if utils::client::is_client_installed(version) {
+        return Err(format!("{} is already installed", version));
+    }
 
-    if utils::client::is_client_installed(version) == false {
-        return Ok(format!("{} is not installed", version));
+    let manifest_version = utils::manifest::fetch_manifest()
+        .await
+        .ok()
+        .and_then(|manifest| manifest.find(version).cloned());
+
+    let verified = if source.is_file() {
+        match &manifest_version {
+            Some(manifest_version) if manifest_version.signed => {
+                let size = fs::metadata(source).map(|m| m.len()).unwrap_or(0);
+
+                let path = source.to_path_buf();
+                let checksum =
+                    tauri::async_runtime::spawn_blocking(move || utils::manifest::sha256_file(&path))
+                        .await
+                        .map_err(|e| format!("Failed to verify the archive: {}", e))??;
+
+                size == manifest_version.size
+                    && checksum.eq_ignore_ascii_case(&manifest_version.sha256)
+            }
+            _ => false,
+        }
+    } else if source.is_dir() {
+        // only archives have a checksum in the manifest to compare against
+        false
+    } else {
+        return Err(format!("{:?} does not exist", source));
+    };
+
+    if !verified && !allow_unverified {
+        return Err(match manifest_version {
+            Some(manifest_version) if !manifest_version.signed => format!(
+                "There is no signed version manifest to check {} against, import it as an unverified custom build instead",
+                version
+            ),
+            Some(_) if source.is_dir() => format!(
+                "Extracted folders can't be checked against the version manifest, import {} as an unverified custom build instead",
+                version
+            ),
+            Some(_) => format!("The archive does not match {} in the version manifest", version),
+            None => format!("{} is not listed in the version manifest", version),
+        });
     }
 
-    fs::remove_dir_all(&version_path)
-        .map_err(|e| format!("Failed to remove the version directory for {}", e))?;
+    let staging_path = utils::install::staging_path(&versions, version);
 
-    Ok(format!("{} was uninstalled from the device", version))
+    if staging_path.exists() {
+        fs::remove_dir_all(&staging_path)
+            .map_err(|e| format!("Failed to clean up a previous installation attempt: {}", e))?;
+    }
+
+    // unpacking takes a while, keep it off the runtime the web server runs on
+    let copied = {
+        let app = app.clone();
+        let version = version.to_string();
+        let source = source.to_path_buf();
+        let staging_path = staging_path.clone();
+        let cancel = cancel.clone();
+
+        tauri::async_runtime::spawn_blocking(move || {
+            let mut last_emit = None;
+
+            let on_entry = |name: &str, index: usize, count: usize| {
+                if !progress_due(&mut last_emit, index == count) {
+                    return;
+                }
+
+                emit_install_progress(
+                    &app,
+                    &InstallProgress {
+                        version: &version,
+                        phase: "extracting",
+                        downloaded: 0,
+                        total: None,
+                        entry: Some(name),
+                        entry_index: index,
+                        entry_count: count,
+                    },
+                );
+            };
+
+            if source.is_file() {
+                let file = File::open(&source)
+                    .map_err(|e| format!("Failed to open the zip file: {}", e))?;
+                let mut archive = ZipArchive::new(file)
+                    .map_err(|e| format!("Failed to read the zip archive: {}", e))?;
+
+                utils::install::extract_archive(&mut archive, &staging_path, &cancel, on_entry)
+            } else {
+                utils::install::copy_directory(&source, &staging_path, &cancel, on_entry)
+            }
+        })
+        .await
+        .map_err(|e| format!("Failed to unpack the build: {}", e))
+        .and_then(|copied| copied)
+    };
+
+    if let Err(e) = copied {
+        let _ = fs::remove_dir_all(&staging_path);
+        return Err(format!("Failed to import {}: {}", version, e));
+    }
+
+    emit_install_progress(
+        app,
+        &InstallProgress {
+            version,
+            phase: "finalizing",
+            downloaded: 0,
+            total: None,
+            entry: None,
+            entry_index: 1,
+            entry_count: 1,
+        },
+    );
+
+    if !verified {
+        println!("importing {} as an unverified custom build", version);
+    }
+
+    finalize_install(app, version, &versions, &staging_path, !verified).await
+}
+
+#[tauri::command]
+async fn verify_client(version: String) -> Result<String, String> {
+    // hashing a whole install takes a while, keep it off the main thread
+    tauri::async_runtime::spawn_blocking(move || verify_version(&version))
+        .await
+        .map_err(|e| format!("Failed to verify the installation: {}", e))?
 }
 
-fn get_versions_file_path() -> std::path::PathBuf {
-    let mut path = utils::appdata::return_versions().expect("Failed to get versions directory");
-    path.push("version_stats.json");
-    path
+fn verify_version(version: &str) -> Result<String, String> {
+    let versions = match utils::appdata::return_versions() {
+        Ok(path) => path,
+        Err(e) => return Err(format!("Failed to get the versions directory: {}", e)),
+    };
+
+    if !utils::client::is_client_installed(version) {
+        return Err(format!("{} is not installed", version));
+    }
+
+    let manifest_path = utils::integrity::file_manifest_path(&versions, version);
+
+    if !manifest_path.exists() {
+        return Err(format!(
+            "No file list was recorded for {}, repair it to create one",
+            version
+        ));
+    }
+
+    let manifest = utils::integrity::load_file_manifest(&manifest_path)?;
+    let report = utils::integrity::verify_files(&versions.join(version), &manifest)?;
+
+    serde_json::to_string(&report).map_err(|e| format!("Failed to serialize the report: {}", e))
+}
+
+/// Downloads the whole archive of `version` again, there is no fetching single
+/// entries out of it, but only rewrites the files that are missing or broken.
+#[tauri::command]
+async fn repair_client(
+    app: AppHandle,
+    jobs: State<'_, InstallJobs>,
+    version: &str,
+) -> Result<String, String> {
+    let cancel = begin_install_job(&jobs, version)?;
+
+    let result = repair_version(&app, version, &cancel).await;
+
+    jobs.jobs.lock().unwrap().remove(version);
+
+    // a cancel arriving after the repair finished changes nothing
+    if result.is_err() && cancel.load(Ordering::Relaxed) {
+        discard_version_archive(version);
+        return Err(format!("Repair of {} was cancelled", version));
+    }
+
+    result
 }
 
-fn load_versions_data() -> VersionsData {
-    let path = get_versions_file_path();
-    if !path.exists() {
-        return VersionsData::default();
+async fn repair_version(
+    app: &AppHandle,
+    version: &str,
+    cancel: &Arc<AtomicBool>,
+) -> Result<String, String> {
+    let versions = match utils::appdata::return_versions() {
+        Ok(path) => path,
+        Err(e) => return Err(format!("Failed to get the versions directory: {}", e)),
+    };
+
+    let version_path = versions.join(version);
+
+    if !version_path.is_dir() {
+        return Err(format!("{} is not installed", version));
+    }
+
+    let file_manifest_path = utils::integrity::file_manifest_path(&versions, version);
+
+    // the official archive would replace an imported build with something else
+    if utils::integrity::load_file_manifest(&file_manifest_path).is_ok_and(|manifest| manifest.unverified) {
+        return Err(format!(
+            "{} is an unverified custom build, reimport it instead of repairing it",
+            version
+        ));
     }
 
-    match fs::read_to_string(&path) {
-        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
-        Err(_) => VersionsData::default(),
+    let manifest_version = utils::manifest::find_version(version).await?;
+    let dest_zip = download_version_archive(app, version, &manifest_version, cancel).await?;
+    let mut archive = open_version_archive(&dest_zip)?;
+
+    // hashing and rewriting the install takes a while, keep it off the
+    // runtime the web server runs on
+    let repair_cancel = cancel.clone();
+    let (files, repaired) = tauri::async_runtime::spawn_blocking(move || {
+        utils::integrity::repair_from_archive(&mut archive, &version_path, &repair_cancel)
+    })
+    .await
+    .map_err(|e| format!("Failed to repair {}: {}", version, e))?
+    .map_err(|e| format!("Failed to repair {}: {}", version, e))?;
+
+    println!("repaired {} files for {}", repaired.len(), version);
+
+    utils::integrity::save_file_manifest(
+        &file_manifest_path,
+        &utils::integrity::FileManifest {
+            version: version.to_string(),
+            // repaired from the archive listed in the manifest
+            unverified: false,
+            files,
+        },
+    )?;
+
+    if let Err(e) = fs::remove_file(dest_zip) {
+        println!("Warning: failed to remove temporary zip file: {}", e);
     }
+
+    serde_json::to_string(&repaired).map_err(|e| format!("Failed to serialize the report: {}", e))
+}
+
+#[tauri::command]
+async fn list_available_versions() -> Result<String, String> {
+    let manifest = utils::manifest::fetch_manifest().await?;
+
+    serde_json::to_string(&manifest.versions)
+        .map_err(|e| format!("Failed to serialize the version manifest: {}", e))
+}
+
+#[tauri::command]
+fn list_installed_versions() -> Result<String, String> {
+    let versions = utils::appdata::return_versions()?;
+
+    serde_json::to_string(&utils::integrity::recorded_versions(&versions))
+        .map_err(|e| format!("Failed to serialize the installed versions: {}", e))
+}
+
+#[tauri::command]
+fn get_mirrors() -> Result<String, String> {
+    serde_json::to_string(&utils::mirrors::load_mirrors())
+        .map_err(|e| format!("Failed to serialize the mirror list: {}", e))
+}
+
+#[tauri::command]
+fn set_mirrors(app: AppHandle, mirrors: Vec<String>) -> Result<(), String> {
+    change_settings(&app, |settings| settings.mirrors = mirrors).map(|_| ())
 }
 
-fn save_versions_data(data: &VersionsData) {
-    let path = get_versions_file_path();
-    if let Ok(content) = serde_json::to_string_pretty(data) {
-        let _ = fs::write(path, content);
+#[tauri::command]
+fn uninstall_client(version: &str) -> Result<String, String> {
+    let versions = match utils::appdata::return_versions() {
+        Ok(path) => path,
+        Err(e) => return Err(format!("Failed to get the versions directory: {}", e)),
+    };
+
+    let version_path = versions.join(version);
+
+    if utils::client::is_client_installed(version) == false {
+        return Ok(format!("{} is not installed", version));
     }
+
+    fs::remove_dir_all(&version_path)
+        .map_err(|e| format!("Failed to remove the version directory for {}", e))?;
+
+    let _ = fs::remove_file(utils::integrity::file_manifest_path(&versions, version));
+
+    Ok(format!("{} was uninstalled from the device", version))
 }
 
 fn get_current_timestamp() -> u64 {
@@ -569,49 +1230,67 @@ fn get_current_timestamp() -> u64 {
 }
 
 #[tauri::command]
-fn get_version_stats(version: &str) -> Result<String, String> {
-    let data = load_versions_data();
-    match data.versions.get(version) {
-        Some(stats) => Ok(serde_json::to_string(stats).unwrap_or_default()),
-        None => Ok(serde_json::to_string(&VersionStats {
-            total_play_time: 0,
-            last_played: 0,
-            is_running: false,
-            start_time: None,
-            size_bytes: 0,
-        })
-        .unwrap_or_default()),
-    }
+fn get_cache_stats() -> Result<String, String> {
+    serde_json::to_string(&utils::cache::stats())
+        .map_err(|e| format!("Failed to serialize the cache stats: {}", e))
 }
 
-fn update_version_stats(version: &str, started: bool) {
-    let mut data = load_versions_data();
-    let current_time = get_current_timestamp();
+#[tauri::command]
+fn clear_cache() -> Result<u64, String> {
+    utils::cache::clear()
+}
 
-    let stats = data
-        .versions
-        .entry(version.to_string())
-        .or_insert(VersionStats {
-            total_play_time: 0,
-            last_played: current_time,
-            is_running: false,
-            start_time: None,
-            size_bytes: 0,
-        });
+#[tauri::command]
+fn set_cache_max_size(app: AppHandle, max_size: u64) -> Result<(), String> {
+    change_settings(&app, |settings| settings.cache_max_size = max_size).map(|_| ())
+}
 
-    if started {
-        stats.is_running = true;
-        stats.start_time = Some(current_time);
-        stats.last_played = current_time;
-    } else if stats.is_running {
-        if let Some(start_time) = stats.start_time {
-            stats.total_play_time += current_time - start_time;
-        }
-        stats.is_running = false;
-        stats.start_time = None;
-    }
+#[tauri::command]
+async fn export_asset_pack(
+    path: String,
+    asset_ids: Option<Vec<u64>>,
+    place_path: Option<String>,
+) -> Result<usize, String> {
+    let destination = utils::pack::pack_path(Path::new(&path));
+
+    tauri::async_runtime::spawn_blocking(move || {
+        utils::pack::export_pack(&destination, asset_ids.as_deref(), place_path.as_deref().map(Path::new))
+    })
+    .await
+    .map_err(|e| format!("Failed to export the asset pack: {}", e))?
+}
+
+#[tauri::command]
+async fn import_asset_pack(path: String) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || utils::pack::import_pack(Path::new(&path)))
+        .await
+        .map_err(|e| format!("Failed to import the asset pack: {}", e))?
+}
+
+#[tauri::command]
+fn list_overrides(version: Option<String>) -> Result<String, String> {
+    let overrides = utils::overrides::list_overrides(version.as_deref())?;
+
+    serde_json::to_string(&overrides).map_err(|e| format!("Failed to serialize the overrides: {}", e))
+}
+
+#[tauri::command]
+fn add_override(asset_id: u64, path: &str, version: Option<String>) -> Result<(), String> {
+    utils::overrides::add_override(asset_id, Path::new(path), version.as_deref())
+}
+
+#[tauri::command]
+fn remove_override(asset_id: u64, version: Option<String>) -> Result<bool, String> {
+    utils::overrides::remove_override(asset_id, version.as_deref())
+}
 
-    save_versions_data(&data);
+#[tauri::command]
+fn get_version_stats(
+    stats: State<'_, utils::stats::StatsStore>,
+    version: &str,
+) -> Result<String, String> {
+    serde_json::to_string(&stats.get(version).unwrap_or_default())
+        .map_err(|e| format!("Failed to serialize the version stats: {}", e))
 }
 
 fn format_size(size: u64) -> String {
@@ -649,9 +1328,11 @@ fn calculate_dir_size(path: &Path) -> std::io::Result<u64> {
 }
 
 #[tauri::command]
-fn get_version_size(version: &str) -> Result<String, String> {
-    let data = load_versions_data();
-    match data.versions.get(version) {
+fn get_version_size(
+    stats: State<'_, utils::stats::StatsStore>,
+    version: &str,
+) -> Result<String, String> {
+    match stats.get(version) {
         Some(stats) => Ok(format_size(stats.size_bytes)),
         None => {
             let versions = utils::appdata::return_versions()
@@ -670,138 +1351,413 @@ fn get_version_size(version: &str) -> Result<String, String> {
     }
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    if std::env::args().any(|arg| arg == "--write-hosts") {
-        use std::io::{Read, Write};
-        let result = (|| -> Result<String, String> {
-            let hosts_path = r"C:\Windows\System32\drivers\etc\hosts";
-
-            let mut contents = String::new();
-            // Open for reading
-            let mut file = std::fs::File::open(hosts_path)
-                .map_err(|e| format!("Failed to open hosts file for reading: {}", e))?;
-            file.read_to_string(&mut contents)
-                .map_err(|e| format!("Failed to read hosts file: {}", e))?;
-
-            if contents.contains(HOSTS_ENTRIES) {
-                return Ok("Hosts file already contains Fluster entries".to_string());
-            }
+fn emit_server_status(app: &AppHandle, status: &utils::server::ServerStatus) {
+    match serde_json::to_string(status) {
+        Ok(payload) => app
+            .emit("server_status", payload)
+            .unwrap_or_else(|e| eprintln!("Failed to emit server status event: {}", e)),
+        Err(e) => eprintln!("Failed to serialize the server status: {}", e),
+    }
+}
 
-            // If not present, open for appending
-            let mut file = std::fs::OpenOptions::new()
-                .append(true)
-                .open(hosts_path)
-                .map_err(|e| format!("Failed to open hosts file for writing: {}", e))?;
+/// Runs the local web server the clients talk to, reporting whether it came up
+/// through `server_status` events instead of taking the whole app down. It is
+/// started again whenever its settings change.
+async fn start_http_server(app: AppHandle) {
+    loop {
+        let config = utils::server::load_server_config();
 
-            writeln!(file, "\n{}", HOSTS_ENTRIES)
-                .map_err(|e| format!("Failed to write to hosts file: {}", e))?;
+        let figment = match utils::server::rocket_figment(&config) {
+            Ok(figment) => figment,
+            Err(e) => {
+                eprintln!("Failed to start HTTP server: {}", e);
+                let status =
+                    utils::server::set_status("failed", &config.address, config.port, Some(e));
+                emit_server_status(&app, &status);
+                return;
+            }
+        };
 
-            Ok("Hosts file updated successfully".to_string())
-        })();
+        let liftoff_app = app.clone();
+
+        let rocket = rocket::custom(figment)
+            .attach(rocket::fairing::AdHoc::on_liftoff("Server status", move |rocket| {
+                let app = liftoff_app.clone();
+
+                Box::pin(async move {
+                    let config = rocket.config();
+                    println!("http server listening on {}:{}", config.address, config.port);
+
+                    let status = utils::server::set_status(
+                        "running",
+                        &config.address.to_string(),
+                        config.port,
+                        None,
+                    );
+                    emit_server_status(&app, &status);
+                })
+            }))
+            .mount(
+                "/",
+                rocket::routes![
+                    ide::toolbox,
+                    ide::start,
+                    ide::upload,
+                    ide::save,
+                    ide::abuse_report,
+                    ide::help,
+                    ide::error_report_dialog,
+                    game::gameserver,
+                    game::machine_configuration,
+                    game::keep_alive_pinger,
+                    game::visit,
+                    game::join,
+                    asset::legacy,
+                    asset::v1,
+                    asset::v2,
+                    r#static::embedded,
+                ],
+            );
+
+        let result = match rocket.ignite().await {
+            Ok(rocket) => {
+                if !utils::server::set_shutdown(Some(rocket.shutdown())) {
+                    return;
+                }
 
-        match result {
-            Ok(msg) => {
-                println!("{}", msg);
-                std::process::exit(0);
-            }
-            Err(e) => {
-                eprintln!("Error writing hosts file: {}", e);
-                std::process::exit(1);
+                rocket.launch().await.map(|_| ())
             }
+            Err(e) => Err(e),
+        };
+
+        utils::server::set_shutdown(None);
+
+        if let Err(e) = result {
+            eprintln!("HTTP server error: {}", e);
+
+            let status = utils::server::set_status(
+                "failed",
+                &config.address,
+                config.port,
+                Some(format!("The web server stopped: {}", e)),
+            );
+            emit_server_status(&app, &status);
+            return;
+        }
+
+        println!("http server stopped");
+        let status = utils::server::set_status("stopped", &config.address, config.port, None);
+
+        if !utils::server::take_restart() {
+            return;
+        }
+
+        emit_server_status(&app, &status);
+        println!("restarting the http server with the new settings");
+    }
+}
+
+/// How long exiting waits for the web server to finish its requests.
+const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Everything that has to happen before the process goes away.
+async fn shut_down(app: &AppHandle) {
+    // waiting on the processes blocks, the runtime still has to drive the
+    // web server shutdown meanwhile
+    let stopped = tauri::async_runtime::spawn_blocking(|| {
+        utils::supervisor::stop_all(utils::supervisor::LaunchKind::Server)
+    })
+    .await;
+
+    if let Err(e) = stopped {
+        eprintln!("Failed to stop the hosted servers: {}", e);
+    }
+
+    // clients are left running on purpose, only their play time stops
+    // counting here since nothing watches them anymore
+    let shutdown_time = get_current_timestamp();
+
+    for launch in utils::supervisor::running() {
+        utils::sessions::record_session(&utils::sessions::Session::from_launch(
+            &launch,
+            shutdown_time,
+            None,
+        ))
+        .unwrap_or_else(|e| eprintln!("{}", e));
+    }
+
+    let stats = app.state::<utils::stats::StatsStore>();
+
+    for version in stats.running_versions() {
+        stats.end_session(&version);
+    }
+
+    utils::cache::flush();
+
+    let started = std::time::Instant::now();
+
+    while utils::server::status().is_some_and(|status| status.state == "running")
+        && started.elapsed() < SHUTDOWN_TIMEOUT
+    {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+}
+
+#[tauri::command]
+fn get_session_history(version: Option<String>, limit: Option<usize>) -> Result<String, String> {
+    serde_json::to_string(&utils::sessions::history(version.as_deref(), limit))
+        .map_err(|e| format!("Failed to serialize the session history: {}", e))
+}
+
+#[tauri::command]
+fn get_session_totals() -> Result<String, String> {
+    serde_json::to_string(&utils::sessions::totals())
+        .map_err(|e| format!("Failed to serialize the session totals: {}", e))
+}
+
+#[tauri::command]
+fn get_running_clients() -> Result<String, String> {
+    serde_json::to_string(&utils::supervisor::running())
+        .map_err(|e| format!("Failed to serialize the running clients: {}", e))
+}
+
+#[tauri::command]
+fn get_server_status() -> Result<String, String> {
+    serde_json::to_string(&utils::server::status())
+        .map_err(|e| format!("Failed to serialize the server status: {}", e))
+}
+
+#[tauri::command]
+fn get_server_config() -> Result<String, String> {
+    serde_json::to_string(&utils::server::load_server_config())
+        .map_err(|e| format!("Failed to serialize the server settings: {}", e))
+}
+
+#[tauri::command]
+fn set_server_config(app: AppHandle, config: utils::server::ServerConfig) -> Result<(), String> {
+    change_settings(&app, |settings| settings.server = config).map(|_| ())
+}
+
+/// Lets everything that already read the settings catch up with a change.
+fn apply_settings(
+    app: &AppHandle,
+    previous: &utils::settings::Settings,
+    settings: &utils::settings::Settings,
+) {
+    if previous.mirrors != settings.mirrors {
+        utils::mirrors::prune_health(&settings.mirrors);
+    }
+
+    if previous.data_dir != settings.data_dir {
+        match utils::appdata::return_versions() {
+            Ok(versions) => app
+                .state::<utils::stats::StatsStore>()
+                .relocate(versions.join("version_stats.json")),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    if previous.cache_dir != settings.cache_dir
+        || (previous.data_dir != settings.data_dir && settings.cache_dir.is_none())
+    {
+        utils::cache::reload();
+    }
+
+    if previous.cache_max_size > settings.cache_max_size {
+        utils::cache::enforce_limit();
+    }
+
+    if previous.server != settings.server && !utils::server::restart() {
+        // it never came up with the old settings, try the new ones
+        tauri::async_runtime::spawn(start_http_server(app.clone()));
+    }
+
+    match serde_json::to_string(settings) {
+        Ok(payload) => app
+            .emit("settings_changed", payload)
+            .unwrap_or_else(|e| eprintln!("Failed to emit settings event: {}", e)),
+        Err(e) => eprintln!("Failed to serialize the settings: {}", e),
+    }
+}
+
+/// How often `settings.json` is checked for edits made outside Fluster.
+const SETTINGS_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Applies edits made to `settings.json` by hand while Fluster runs, the same
+/// way changes made through the settings screen are.
+fn watch_settings(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(SETTINGS_POLL_INTERVAL);
+
+        if !utils::settings::changed_on_disk() {
+            continue;
         }
+
+        // pending access times belong to the index at the current cache location
+        utils::cache::flush();
+
+        let (previous, settings) = utils::settings::reload();
+
+        if previous != settings {
+            println!("settings.json was edited, applying the changes");
+            apply_settings(&app, &previous, &settings);
+        }
+    });
+}
+
+fn change_settings(
+    app: &AppHandle,
+    change: impl FnOnce(&mut utils::settings::Settings),
+) -> Result<utils::settings::Settings, String> {
+    // pending access times belong to the index at the current cache location
+    utils::cache::flush();
+
+    let (previous, settings) = utils::settings::modify(change)?;
+    apply_settings(app, &previous, &settings);
+
+    Ok(settings)
+}
+
+#[tauri::command]
+fn get_settings() -> Result<String, String> {
+    serde_json::to_string(&utils::settings::current())
+        .map_err(|e| format!("Failed to serialize the settings: {}", e))
+}
+
+#[tauri::command]
+fn update_settings(
+    app: AppHandle,
+    jobs: State<'_, InstallJobs>,
+    settings: utils::settings::Settings,
+) -> Result<String, String> {
+    let moving = settings.data_dir != utils::settings::current().data_dir;
+
+    // held until the change is saved, so no install starts at the old location
+    // while the data location moves
+    let active_jobs = jobs.jobs.lock().unwrap();
+
+    // running clients keep using the versions at the old location
+    if moving && !utils::supervisor::running().is_empty() {
+        return Err("Close every running client before moving the data location".to_string());
     }
 
-    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
-
-    let server_handle = tokio::spawn(async move {
-        let config = rocket::Config::figment()
-            .merge(("port", 80))
-            .merge(("address", "127.0.0.1"))
-            .merge(("shutdown.timeout", 0))
-            .merge(("workers", 1));
-
-        let rocket = rocket::custom(config).mount(
-            "/",
-            rocket::routes![
-                ide::toolbox,
-                ide::start,
-                ide::upload,
-                ide::save,
-                ide::abuse_report,
-                ide::help,
-                ide::error_report_dialog,
-                game::gameserver,
-                game::machine_configuration,
-                game::keep_alive_pinger,
-                game::visit,
-                game::join,
-                asset::legacy,
-                asset::v1,
-                asset::v2,
-                r#static::embedded,
-            ],
+    // installs and repairs would finish into the old location
+    if moving && !active_jobs.is_empty() {
+        return Err(
+            "Wait for every download, install and repair to finish before moving the data location"
+                .to_string(),
         );
+    }
 
-        match rocket.ignite().await {
-            Ok(rocket) => {
-                if let Err(e) = rocket.launch().await {
-                    eprintln!("HTTP server error: {}", e);
-                    if e.to_string().contains("address already in use")
-                        || e.to_string().contains("permission denied")
-                    {
-                        eprintln!("Port 80 is either in use or requires admin privileges.");
-                        eprintln!("Please ensure no other web server is running on port 80");
-                        eprintln!("On Unix systems, try running with sudo");
-                        eprintln!("On Windows, run as Administrator");
-                        std::process::exit(1);
-                    }
-                }
+    let settings = change_settings(&app, |current| *current = settings)?;
+
+    drop(active_jobs);
+
+    serde_json::to_string(&settings)
+        .map_err(|e| format!("Failed to serialize the settings: {}", e))
+}
+
+#[tauri::command]
+fn get_data_location() -> Result<String, String> {
+    serde_json::to_string(&utils::appdata::return_location()?)
+        .map_err(|e| format!("Failed to serialize the data location: {}", e))
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    let hosts_flag = std::env::args().find(|arg| arg == "--write-hosts" || arg == "--remove-hosts");
+
+    if let Some(flag) = hosts_flag {
+        let result = if flag == "--write-hosts" {
+            utils::hosts::write_hosts_file()
+        } else {
+            utils::hosts::clean_hosts_file()
+        };
+
+        match result {
+            Ok(changed) => {
+                println!("hosts file {}", if changed { "updated" } else { "already up to date" });
+                std::process::exit(0);
             }
             Err(e) => {
-                eprintln!("Failed to start HTTP server: {}", e);
-                if e.to_string().contains("address already in use")
-                    || e.to_string().contains("permission denied")
-                {
-                    eprintln!("Port 80 is either in use or requires admin privileges.");
-                    eprintln!("Please ensure no other web server is running on port 80");
-                    eprintln!("On Unix systems, try running with sudo");
-                    eprintln!("On Windows, run as Administrator");
-                    std::process::exit(1);
-                }
+                eprintln!("Error writing hosts file: {}", e);
+                std::process::exit(1);
             }
         }
-    });
+    }
 
     let app = tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            let versions = utils::appdata::return_versions()?;
+            app.manage(utils::stats::StatsStore::open(
+                versions.join("version_stats.json"),
+            ));
+
+            tauri::async_runtime::spawn(start_http_server(app.handle().clone()));
+            watch_settings(app.handle().clone());
+            Ok(())
+        })
+        .manage(InstallJobs::default())
         .invoke_handler(tauri::generate_handler![
             start_server,
             start_listening,
             get_device_username,
             fluster_setup,
             setup_hosts_file,
+            uninstall_hosts_entries,
+            check_domain_resolution,
             is_fluster_setup,
             is_version_installed,
             launch_client,
             launch_server_connection,
             install_client,
+            cancel_install,
+            list_available_versions,
+            list_installed_versions,
+            import_client,
+            verify_client,
+            repair_client,
+            get_mirrors,
+            set_mirrors,
             uninstall_client,
             get_version_stats,
+            get_cache_stats,
+            clear_cache,
+            set_cache_max_size,
+            export_asset_pack,
+            import_asset_pack,
+            list_overrides,
+            add_override,
+            remove_override,
             get_version_size,
+            get_server_status,
+            get_running_clients,
+            get_session_history,
+            get_session_totals,
+            get_server_config,
+            set_server_config,
+            get_settings,
+            update_settings,
+            get_data_location,
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application");
 
-    let shutdown_tx = std::sync::Arc::new(std::sync::Mutex::new(Some(shutdown_tx)));
-    let shutdown_tx_clone = shutdown_tx.clone();
+    let exiting = AtomicBool::new(false);
 
-    app.run(move |_app_handle, event| match event {
-        tauri::RunEvent::ExitRequested { api, .. } => {
-            if let Some(tx) = shutdown_tx_clone.lock().unwrap().take() {
-                let _ = tx.send(());
-            }
+    app.run(move |app_handle, event| match event {
+        // the first request starts the shutdown, the exit it ends with comes
+        // back through here and is let through
+        tauri::RunEvent::ExitRequested { api, .. } if !exiting.swap(true, Ordering::Relaxed) => {
             api.prevent_exit();
+            utils::server::stop();
+
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                shut_down(&app_handle).await;
+                app_handle.exit(0);
+            });
         }
         _ => {}
     });
@@ -1,85 +1,283 @@
-use dirs::data_local_dir;
+use std::time::Duration;
 
-async fn obtain_asset_from_roblox(id: usize) -> Result<Vec<u8>, String> {
-    let data = match data_local_dir() {
-        Some(path) => path,
-        None => return Err("Failed to get local data directory".to_string()),
+use rocket::http::{ContentType, Status};
+use tokio::task::JoinSet;
+
+use crate::utils::cache::{self, AssetKey};
+use crate::utils::{lan_assets, overrides, settings};
+
+/// How many assets are fetched at once when warming the cache for a place.
+const PREFETCH_CONCURRENCY: usize = 8;
+
+const UPSTREAM_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Guesses the type of an asset from its first bytes, for cached assets and
+/// upstream responses that don't say what they are.
+fn sniff_content_type(bytes: &[u8]) -> ContentType {
+    let media_type = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        ("image", "png")
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        ("image", "jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        ("image", "gif")
+    } else if bytes.starts_with(b"DDS ") {
+        ("image", "vnd-ms.dds")
+    } else if bytes.starts_with(b"OggS") {
+        ("audio", "ogg")
+    } else if bytes.starts_with(b"ID3") || bytes.starts_with(&[0xff, 0xfb]) {
+        ("audio", "mpeg")
+    } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WAVE" {
+        ("audio", "wav")
+    } else if bytes.starts_with(b"<roblox!") {
+        // binary place and model files
+        ("application", "octet-stream")
+    } else if bytes.starts_with(b"<roblox") || bytes.starts_with(b"<?xml") {
+        ("application", "xml")
+    } else {
+        ("application", "octet-stream")
     };
 
-    let cache_dir = data.join("Fluster").join("cache");
+    ContentType::new(media_type.0, media_type.1)
+}
+
+fn upstream_content_type(response: &reqwest::Response) -> Option<ContentType> {
+    let value = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)?
+        .to_str()
+        .ok()?;
+
+    ContentType::parse_flexible(value)
+}
 
-    if !cache_dir.exists() {
-        std::fs::create_dir_all(&cache_dir)
-            .map_err(|e| format!("Creating the Fluster cache directory failed: {}", e))?;
+/// Picks the revision an asset request asks for, `assetversionid` names one
+/// exactly so it wins over `id` and `version`.
+fn asset_key(
+    id: Option<u64>,
+    version: Option<u64>,
+    asset_version_id: Option<u64>,
+) -> Result<AssetKey, Status> {
+    match (id, asset_version_id) {
+        (_, Some(asset_version_id)) => Ok(AssetKey::AssetVersion(asset_version_id)),
+        (Some(id), None) => Ok(AssetKey::Asset { id, version }),
+        (None, None) => Err(Status::BadRequest),
     }
+}
+
+fn upstream_url(asset: AssetKey) -> String {
+    format!("{}?{}", settings::current().asset_endpoint, asset.query())
+}
+
+fn read_override(asset: AssetKey) -> Option<(ContentType, Vec<u8>)> {
+    let AssetKey::Asset { id, .. } = asset else {
+        return None;
+    };
+
+    let path = overrides::find_override(id)?;
+
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to read the override {:?}: {}", path, e);
+            return None;
+        }
+    };
+
+    println!("serving asset {} from override {:?}", id, path);
+
+    let content_type = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(ContentType::from_extension)
+        .unwrap_or_else(|| sniff_content_type(&bytes));
+
+    Some((content_type, bytes))
+}
+
+/// Serves `asset` from the overrides, the cache, a Fluster host on the LAN when
+/// `ask_lan` is set, or Roblox, in that order.
+async fn obtain_asset_from_roblox(
+    asset: AssetKey,
+    ask_lan: bool,
+) -> Result<(ContentType, Vec<u8>), Status> {
+    // overrides replace every version of an asset
+    if let Some(asset) = read_override(asset) {
+        return Ok(asset);
+    }
+
+    if let Some((content_type, bytes)) = cache::get(asset) {
+        let content_type =
+            ContentType::parse_flexible(&content_type).unwrap_or_else(|| sniff_content_type(&bytes));
+
+        return Ok((content_type, bytes));
+    }
+
+    if ask_lan {
+        if let Some((content_type, bytes)) = lan_assets::fetch_from_lan(asset).await {
+            let content_type = content_type
+                .as_deref()
+                .and_then(ContentType::parse_flexible)
+                .unwrap_or_else(|| sniff_content_type(&bytes));
 
-    let hash = format!("{:x}", md5::compute(id.to_string()));
-    let cache_file = cache_dir.join(hash);
+            if let Err(e) = cache::insert(asset, &content_type.to_string(), &bytes) {
+                eprintln!("{}", e);
+            }
 
-    if cache_file.exists() {
-        let bytes = std::fs::read(&cache_file)
-            .map_err(|e| format!("Reading the cache file failed: {}", e))?;
-        return Ok(bytes);
+            return Ok((content_type, bytes));
+        }
     }
 
-    let url = format!("https://assetdelivery.roblox.com/v1/asset?id={}", id);
+    let url = upstream_url(asset);
 
-    let client = reqwest::Client::new();
-    let response = client
+    let client = reqwest::Client::builder()
+        .connect_timeout(UPSTREAM_CONNECT_TIMEOUT)
+        .timeout(UPSTREAM_TIMEOUT)
+        .build()
+        .map_err(|e| {
+            eprintln!("Failed to create the asset client: {}", e);
+            Status::InternalServerError
+        })?;
+
+    let response = match client
         .get(&url)
         .header("User-Agent", "Roblox/WinInet")
         .send()
         .await
-        .unwrap();
+    {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("Failed to fetch asset {:?}: {}", asset, e);
+            return Err(if e.is_timeout() {
+                Status::GatewayTimeout
+            } else {
+                Status::BadGateway
+            });
+        }
+    };
 
     match response.status() {
         reqwest::StatusCode::OK => {
-            let text = response.text().await;
-            match text {
-                Ok(text) => {
-                    let bytes = text.into_bytes();
-
-                    std::fs::write(&cache_file, &bytes)
-                        .map_err(|e| format!("Writing the cache file failed: {}", e))?;
+            let content_type = upstream_content_type(&response);
 
-                    Ok(bytes)
+            let bytes = match response.bytes().await {
+                Ok(bytes) => bytes.to_vec(),
+                Err(e) => {
+                    eprintln!("Failed to read asset data for {:?}: {}", asset, e);
+                    return Err(Status::BadGateway);
                 }
-                Err(_) => Err("Failed to read asset data".to_string()),
+            };
+
+            let content_type = content_type.unwrap_or_else(|| sniff_content_type(&bytes));
+
+            if let Err(e) = cache::insert(asset, &content_type.to_string(), &bytes) {
+                // the asset can still be served, it just gets fetched again next time
+                eprintln!("{}", e);
             }
+
+            Ok((content_type, bytes))
         }
 
-        reqwest::StatusCode::NOT_FOUND => Err("Asset not found".to_string()),
+        status if status.is_client_error() => {
+            println!("asset {:?} returned {}", asset, status);
+            Err(Status::from_code(status.as_u16()).unwrap_or(Status::NotFound))
+        }
 
-        _ => Err("Failed to fetch asset".to_string()),
+        status => {
+            eprintln!("Failed to fetch asset {:?} for error code {}", asset, status);
+            Err(Status::BadGateway)
+        }
     }
 }
 
-#[rocket::get("/asset?<id>")]
-pub async fn legacy(id: usize) -> Result<Vec<u8>, String> {
-    let result = obtain_asset_from_roblox(id).await;
-    match result {
-        Ok(bytes) => Ok(bytes),
+/// Fetches every asset in `assets` that isn't cached or overridden yet, a few at
+/// a time. `on_progress` gets the number of finished, total and failed assets.
+pub(crate) async fn prefetch_assets(
+    assets: Vec<AssetKey>,
+    mut on_progress: impl FnMut(usize, usize, usize),
+) -> (usize, usize) {
+    let mut pending: Vec<AssetKey> = assets
+        .into_iter()
+        .filter(|asset| !cache::contains(*asset))
+        .filter(|asset| match asset {
+            AssetKey::Asset { id, .. } => overrides::find_override(*id).is_none(),
+            AssetKey::AssetVersion(_) => true,
+        })
+        .collect();
+
+    // fetched from the back, keep the order they appear in the place
+    pending.reverse();
+
+    let total = pending.len();
+    let mut completed = 0;
+    let mut failed = 0;
+    let mut tasks = JoinSet::new();
+
+    on_progress(completed, total, failed);
 
-        Err(err) => Err(err),
+    loop {
+        while tasks.len() < PREFETCH_CONCURRENCY {
+            let Some(asset) = pending.pop() else {
+                break;
+            };
+
+            tasks.spawn(obtain_asset_from_roblox(asset, true));
+        }
+
+        let Some(result) = tasks.join_next().await else {
+            break;
+        };
+
+        completed += 1;
+
+        if !matches!(result, Ok(Ok(_))) {
+            failed += 1;
+        }
+
+        on_progress(completed, total, failed);
     }
+
+    println!("prefetched {} assets, {} failed", total - failed, failed);
+
+    (completed - failed, failed)
 }
 
-#[rocket::get("/v1/asset/<id>")]
-pub async fn v1(id: usize) -> Result<Vec<u8>, String> {
-    let result = obtain_asset_from_roblox(id).await;
-    match result {
-        Ok(bytes) => Ok(bytes),
+// requests for `/asset/?id=` land here too, Rocket ignores the empty segment
+#[rocket::get("/asset?<id>&<version>&<assetversionid>")]
+pub async fn legacy(
+    id: Option<u64>,
+    version: Option<u64>,
+    assetversionid: Option<u64>,
+) -> Result<(ContentType, Vec<u8>), Status> {
+    obtain_asset_from_roblox(asset_key(id, version, assetversionid)?, true).await
+}
 
-        Err(err) => Err(err),
-    }
+/// Shares this instance's assets with the rest of the LAN while it hosts.
+/// Never asks other hosts itself, so two hosts can't bounce a request between
+/// each other.
+#[rocket::get("/lan/asset?<id>&<version>&<assetversionid>")]
+pub async fn lan(
+    id: Option<u64>,
+    version: Option<u64>,
+    assetversionid: Option<u64>,
+) -> Result<(ContentType, Vec<u8>), Status> {
+    obtain_asset_from_roblox(asset_key(id, version, assetversionid)?, false).await
 }
 
-#[rocket::get("/v2/asset/<id>")]
-pub async fn v2(id: usize) -> Result<Vec<u8>, String> {
-    let result = obtain_asset_from_roblox(id).await;
-    match result {
-        Ok(bytes) => Ok(bytes),
+#[rocket::get("/v1/asset/<id>?<version>&<assetversionid>")]
+pub async fn v1(
+    id: u64,
+    version: Option<u64>,
+    assetversionid: Option<u64>,
+) -> Result<(ContentType, Vec<u8>), Status> {
+    obtain_asset_from_roblox(asset_key(Some(id), version, assetversionid)?, true).await
+}
 
-        Err(err) => Err(err),
-    }
+#[rocket::get("/v2/asset/<id>?<version>&<assetversionid>")]
+pub async fn v2(
+    id: u64,
+    version: Option<u64>,
+    assetversionid: Option<u64>,
+) -> Result<(ContentType, Vec<u8>), Status> {
+    obtain_asset_from_roblox(asset_key(Some(id), version, assetversionid)?, true).await
 }
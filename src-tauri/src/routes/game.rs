@@ -1,5 +1,7 @@
 #![allow(non_snake_case)]
 
+use crate::utils::server;
+
 #[rocket::post("/Game/MachineConfiguration.ashx")]
 pub async fn machine_configuration() {
     "OK".to_string();
@@ -12,12 +14,12 @@ pub async fn keep_alive_pinger() {
 
 #[rocket::get("/game/visit.ashx")]
 pub async fn visit() -> String {
-    return include_str!("../assets/storage/visit.lua").to_string();
+    return include_str!("../assets/storage/visit.lua").replace("{{base_url}}", &server::base_url());
 }
 
 #[rocket::get("/game/gameserver.ashx")]
 pub async fn gameserver() -> String {
-    return include_str!("../assets/storage/gameserver.lua").to_string();
+    return include_str!("../assets/storage/gameserver.lua").replace("{{base_url}}", &server::base_url());
 }
 
 #[rocket::get("/game/join.ashx?<UserID>&<serverPort>&<serverIP>")]
@@ -29,6 +31,7 @@ pub async fn join(UserID: usize, serverPort: usize, serverIP: Option<String>) ->
     result = result.replace("{{user_id}}", &UserID.to_string());
     result = result.replace("{{server_port}}", &serverPort.to_string());
     result = result.replace("{{server_ip}}", ip);
+    result = result.replace("{{base_url}}", &server::base_url());
 
     return result;
 }
\ No newline at end of file
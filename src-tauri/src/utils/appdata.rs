@@ -1,13 +1,92 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use dirs::data_local_dir;
+use serde::Serialize;
+
+use crate::utils::settings;
+
+const DATA_DIR_VARIABLE: &str = "FLUSTER_DATA_DIR";
+
+/// Fluster runs portable when this file sits next to its executable.
+const PORTABLE_MARKER: &str = "fluster.portable";
+const PORTABLE_DATA: &str = "FlusterData";
+
+const CACHE_DIR: &str = "fluster-cache";
+
+fn portable_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let exe_dir = exe.parent()?;
+
+    if exe_dir.join(PORTABLE_MARKER).is_file() {
+        Some(exe_dir.join(PORTABLE_DATA))
+    } else {
+        None
+    }
+}
+
+fn base_dir() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(DATA_DIR_VARIABLE) {
+        return Some(PathBuf::from(path));
+    }
+
+    if let Some(path) = portable_dir() {
+        return Some(path);
+    }
+
+    return Some(data_local_dir()?.join("Fluster"));
+}
+
+/// `FLUSTER_DATA_DIR` wins, then the `data_dir` setting, which is resolved
+/// from where the settings live so portable installs can point elsewhere on
+/// the same drive.
+fn data_dir() -> Option<PathBuf> {
+    let base = base_dir()?;
+
+    if std::env::var_os(DATA_DIR_VARIABLE).is_some() {
+        return Some(base);
+    }
+
+    match settings::current().data_dir {
+        Some(data_dir) => Some(base.join(data_dir)),
+        None => Some(base),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DataLocation {
+    pub settings_dir: PathBuf,
+    pub data_dir: PathBuf,
+    pub portable: bool,
+}
+
+pub fn return_location() -> Result<DataLocation, String> {
+    return Ok(DataLocation {
+        settings_dir: return_settings_dir()?,
+        data_dir: return_appdata()?,
+        portable: std::env::var_os(DATA_DIR_VARIABLE).is_none() && portable_dir().is_some(),
+    });
+}
+
+/// Version names end up as directory names under `versions/`, so anything that
+/// isn't a single plain component is refused.
+pub fn validate_version(version: &str) -> Result<(), String> {
+    if version.is_empty()
+        || version == "."
+        || version == ".."
+        || version.contains(['/', '\\'])
+        || Path::new(version).is_absolute()
+    {
+        return Err(format!("{} is not a valid version name", version));
+    }
+
+    return Ok(());
+}
 
 pub fn is_fluster_setup() -> bool {
-    let data = match data_local_dir() {
+    let fluster_path = match data_dir() {
         Some(path) => path,
         None => return false,
     };
 
-    let fluster_path = data.join("Fluster");
     let versions_path = fluster_path.join("versions");
     let downloads_path = fluster_path.join("downloads");
 
@@ -16,15 +95,27 @@ pub fn is_fluster_setup() -> bool {
         downloads_path.exists() && downloads_path.is_dir();
 }
 
-pub fn return_appdata() -> Result<PathBuf, String> {
-    let data = data_local_dir()
+/// Where `settings.json` lives. It can't follow the `data_dir` setting, since
+/// that is read from it.
+pub fn return_settings_dir() -> Result<PathBuf, String> {
+    let settings = base_dir()
         .ok_or("Failed to get the local data directory.")?;
 
-    let appdata = data.join("Fluster");
+    if !settings.exists() {
+        std::fs::create_dir_all(&settings)
+            .map_err(|e| format!("Failed to create the Fluster data directory {:?}: {}", settings, e))?;
+    }
+
+    return Ok(settings);
+}
+
+pub fn return_appdata() -> Result<PathBuf, String> {
+    let appdata = data_dir()
+        .ok_or("Failed to get the local data directory.")?;
 
     if !appdata.exists() {
         std::fs::create_dir_all(&appdata)
-            .expect("Failed to create the Fluster AppData directory.");
+            .map_err(|e| format!("Failed to create the Fluster data directory {:?}: {}", appdata, e))?;
     }
 
     return Ok(appdata);
@@ -36,7 +127,7 @@ pub fn return_versions() -> Result<PathBuf, String> {
 
     if !versions.exists() {
         std::fs::create_dir_all(&versions)
-            .expect("Failed to create the Fluster versions directory.");
+            .map_err(|e| format!("Failed to create the Fluster versions directory: {}", e))?;
     }
 
     return Ok(versions);
@@ -48,20 +139,24 @@ pub fn return_downloads() -> Result<PathBuf, String> {
 
     if !downloads.exists() {
         std::fs::create_dir_all(&downloads)
-            .expect("Failed to create the Fluster downloads directory.");
+            .map_err(|e| format!("Failed to create the Fluster downloads directory: {}", e))?;
     }
 
     return Ok(downloads);
 }
 
 pub fn return_cache() -> Result<PathBuf, String> {
-    let appdata = return_appdata()?;
-    let cache = appdata.join("cache");
+    // clearing the cache empties its directory, which must not be one the
+    // user keeps anything else in
+    let cache = match settings::current().cache_dir {
+        Some(cache) => cache.join(CACHE_DIR),
+        None => return_appdata()?.join("cache"),
+    };
 
     if !cache.exists() {
         std::fs::create_dir_all(&cache)
-            .expect("Failed to create the Fluster cache directory.");
+            .map_err(|e| format!("Failed to create the cache directory {:?}: {}", cache, e))?;
     }
 
     return Ok(cache);
-}
\ No newline at end of file
+}
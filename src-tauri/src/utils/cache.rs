@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{appdata, settings};
+
+/// Access times change on every hit, so they are only written back to the
+/// index this often instead of on each request.
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+const INDEX_FILE: &str = "index.json";
+
+/// What identifies a cached asset, either an asset id pinned to an optional
+/// version or an `assetversionid`, which names one revision on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetKey {
+    Asset { id: u64, version: Option<u64> },
+    AssetVersion(u64),
+}
+
+impl AssetKey {
+    pub fn cache_key(&self) -> String {
+        match self {
+            AssetKey::Asset { id, version: None } => id.to_string(),
+            AssetKey::Asset { id, version: Some(version) } => format!("{}-{}", id, version),
+            AssetKey::AssetVersion(asset_version_id) => format!("v{}", asset_version_id),
+        }
+    }
+
+    /// The query string asking an asset endpoint for this revision.
+    pub fn query(&self) -> String {
+        match self {
+            AssetKey::Asset { id, version: None } => format!("id={}", id),
+            AssetKey::Asset { id, version: Some(version) } => format!("id={}&version={}", id, version),
+            AssetKey::AssetVersion(asset_version_id) => format!("assetversionid={}", asset_version_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub asset_id: Option<u64>,
+    pub version: Option<u64>,
+    #[serde(default)]
+    pub asset_version_id: Option<u64>,
+    pub content_type: String,
+    pub fetched_at: u64,
+    pub size: u64,
+    pub last_access: u64,
+}
+
+impl CacheEntry {
+    pub fn asset_key(&self) -> Option<AssetKey> {
+        match (self.asset_id, self.asset_version_id) {
+            (_, Some(asset_version_id)) => Some(AssetKey::AssetVersion(asset_version_id)),
+            (Some(id), None) => Some(AssetKey::Asset {
+                id,
+                version: self.version,
+            }),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheIndex {
+    #[serde(default)]
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl CacheIndex {
+    fn total_size(&self) -> u64 {
+        self.entries.values().map(|entry| entry.size).sum()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub total_size: u64,
+    pub max_size: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+struct CacheState {
+    index: CacheIndex,
+    dirty: bool,
+    last_saved: Instant,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+static CACHE: Mutex<Option<CacheState>> = Mutex::new(None);
+
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn max_size() -> u64 {
+    settings::current().cache_max_size
+}
+
+fn entry_path(key: &str) -> Result<PathBuf, String> {
+    Ok(appdata::return_cache()?.join(format!("{:x}", md5::compute(key))))
+}
+
+fn load_index() -> CacheIndex {
+    let Ok(cache) = appdata::return_cache() else {
+        return CacheIndex::default();
+    };
+
+    let path = cache.join(INDEX_FILE);
+
+    // files from before the index are picked up as they get requested
+    let Ok(content) = fs::read_to_string(&path) else {
+        return CacheIndex::default();
+    };
+
+    match serde_json::from_str(&content) {
+        Ok(index) => index,
+        Err(e) => {
+            // keep what is left of it rather than silently starting over
+            let backup = path.with_extension(format!("json.{}.bak", current_timestamp()));
+
+            match fs::copy(&path, &backup) {
+                Ok(_) => eprintln!("The cache index is corrupted ({}), kept a copy at {:?}", e, backup),
+                Err(copy_error) => eprintln!(
+                    "The cache index is corrupted ({}), and failed to keep a copy: {}",
+                    e, copy_error
+                ),
+            }
+
+            CacheIndex::default()
+        }
+    }
+}
+
+fn save_index(state: &mut CacheState) {
+    let result = appdata::return_cache().and_then(|cache| {
+        let content = serde_json::to_string(&state.index)
+            .map_err(|e| format!("Failed to serialize the cache index: {}", e))?;
+
+        // swapped in whole, a crash mid-write leaves the previous index behind
+        let path = cache.join(INDEX_FILE);
+        let temp_path = path.with_extension("json.tmp");
+
+        fs::write(&temp_path, content)
+            .and_then(|_| fs::rename(&temp_path, &path))
+            .map_err(|e| format!("Failed to save the cache index: {}", e))
+    });
+
+    match result {
+        Ok(()) => {
+            state.dirty = false;
+            state.last_saved = Instant::now();
+        }
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+fn with_state<T>(f: impl FnOnce(&mut CacheState) -> T) -> T {
+    let mut guard = CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let state = guard.get_or_insert_with(|| CacheState {
+        index: load_index(),
+        dirty: false,
+        last_saved: Instant::now(),
+        hits: 0,
+        misses: 0,
+        evictions: 0,
+    });
+
+    f(state)
+}
+
+/// Assets cached before there was an index were stored under the same name an
+/// unversioned asset id still maps to, so they are indexed on first use
+/// instead of being fetched again.
+fn adopt(state: &mut CacheState, asset: AssetKey, key: &str) -> bool {
+    let AssetKey::Asset { id, version: None } = asset else {
+        return false;
+    };
+
+    let Some(metadata) = entry_path(key)
+        .ok()
+        .and_then(|path| fs::metadata(path).ok())
+        .filter(|metadata| metadata.is_file() && metadata.len() > 0)
+    else {
+        return false;
+    };
+
+    let now = current_timestamp();
+
+    let fetched_at = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(now, |modified| modified.as_secs());
+
+    state.index.entries.insert(
+        key.to_string(),
+        CacheEntry {
+            asset_id: Some(id),
+            version: None,
+            asset_version_id: None,
+            // unknown, sniffed from the bytes when served
+            content_type: String::new(),
+            fetched_at,
+            size: metadata.len(),
+            last_access: now,
+        },
+    );
+
+    println!("indexed asset {} cached before the index existed", key);
+
+    evict(state, Some(key));
+    state.dirty = true;
+
+    true
+}
+
+/// Drops the least recently used entries until the cache fits in its limit,
+/// never `keep`, the entry that was just added.
+fn evict(state: &mut CacheState, keep: Option<&str>) {
+    let mut total = state.index.total_size();
+    let max_size = max_size();
+
+    if total <= max_size {
+        return;
+    }
+
+    let mut entries: Vec<(String, u64, u64, u64)> = state
+        .index
+        .entries
+        .iter()
+        .filter(|(key, _)| Some(key.as_str()) != keep)
+        .map(|(key, entry)| (key.clone(), entry.last_access, entry.fetched_at, entry.size))
+        .collect();
+
+    entries.sort_by_key(|(_, last_access, fetched_at, _)| (*last_access, *fetched_at));
+
+    for (key, _, _, size) in entries {
+        if total <= max_size {
+            break;
+        }
+
+        if let Ok(path) = entry_path(&key) {
+            let _ = fs::remove_file(path);
+        }
+
+        state.index.entries.remove(&key);
+        state.evictions += 1;
+        total = total.saturating_sub(size);
+
+        println!("evicted asset {} from the cache", key);
+    }
+
+    state.dirty = true;
+}
+
+/// Returns the content type and bytes of a cached asset.
+pub fn get(asset: AssetKey) -> Option<(String, Vec<u8>)> {
+    let key = asset.cache_key();
+
+    let content_type = with_state(|state| {
+        if !state.index.entries.contains_key(&key) && !adopt(state, asset, &key) {
+            state.misses += 1;
+            return None;
+        }
+
+        let entry = state.index.entries.get_mut(&key)?;
+        entry.last_access = current_timestamp();
+        state.dirty = true;
+
+        Some(entry.content_type.clone())
+    })?;
+
+    let bytes = entry_path(&key).and_then(|path| {
+        fs::read(path).map_err(|e| format!("Reading the cache file failed: {}", e))
+    });
+
+    with_state(|state| {
+        match &bytes {
+            Ok(_) => state.hits += 1,
+            Err(e) => {
+                // the file went missing, forget it so it gets fetched again
+                eprintln!("{}", e);
+                state.index.entries.remove(&key);
+                state.misses += 1;
+            }
+        }
+
+        if state.dirty && state.last_saved.elapsed() >= SAVE_INTERVAL {
+            save_index(state);
+        }
+    });
+
+    bytes.ok().map(|bytes| (content_type, bytes))
+}
+
+pub fn contains(asset: AssetKey) -> bool {
+    let key = asset.cache_key();
+    with_state(|state| state.index.entries.contains_key(&key) || adopt(state, asset, &key))
+}
+
+/// Reads a cached asset without counting it as a hit or touching its access time.
+pub fn read(asset: AssetKey) -> Option<Vec<u8>> {
+    fs::read(entry_path(&asset.cache_key()).ok()?).ok()
+}
+
+pub fn entries() -> Vec<CacheEntry> {
+    with_state(|state| state.index.entries.values().cloned().collect())
+}
+
+pub fn insert(asset: AssetKey, content_type: &str, bytes: &[u8]) -> Result<(), String> {
+    if store(asset, content_type, bytes)? {
+        with_state(save_index);
+    }
+
+    Ok(())
+}
+
+/// Like [`insert`], but leaves saving the index to a later [`flush`] so bulk
+/// imports don't rewrite it once per asset. Returns false when the asset is
+/// too big to be cached at all.
+pub fn insert_deferred(asset: AssetKey, content_type: &str, bytes: &[u8]) -> Result<bool, String> {
+    store(asset, content_type, bytes)
+}
+
+fn store(asset: AssetKey, content_type: &str, bytes: &[u8]) -> Result<bool, String> {
+    let key = asset.cache_key();
+    let size = bytes.len() as u64;
+
+    if size > max_size() {
+        return Ok(false);
+    }
+
+    fs::write(entry_path(&key)?, bytes)
+        .map_err(|e| format!("Writing the cache file failed: {}", e))?;
+
+    with_state(|state| {
+        let now = current_timestamp();
+
+        let (asset_id, version, asset_version_id) = match asset {
+            AssetKey::Asset { id, version } => (Some(id), version, None),
+            AssetKey::AssetVersion(asset_version_id) => (None, None, Some(asset_version_id)),
+        };
+
+        state.index.entries.insert(
+            key.clone(),
+            CacheEntry {
+                asset_id,
+                version,
+                asset_version_id,
+                content_type: content_type.to_string(),
+                fetched_at: now,
+                size,
+                last_access: now,
+            },
+        );
+
+        state.dirty = true;
+        evict(state, Some(&key));
+    });
+
+    Ok(true)
+}
+
+pub fn stats() -> CacheStats {
+    with_state(|state| CacheStats {
+        entries: state.index.entries.len(),
+        total_size: state.index.total_size(),
+        max_size: max_size(),
+        hits: state.hits,
+        misses: state.misses,
+        evictions: state.evictions,
+    })
+}
+
+/// Removes every cached asset and returns how many bytes were freed.
+pub fn clear() -> Result<u64, String> {
+    let cache = appdata::return_cache()?;
+
+    with_state(|state| {
+        let freed = state.index.total_size();
+
+        for entry in fs::read_dir(&cache)
+            .map_err(|e| format!("Failed to read the cache directory: {}", e))?
+            .flatten()
+        {
+            if entry.path().is_file() && entry.file_name() != INDEX_FILE {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+
+        state.index.entries.clear();
+        save_index(state);
+
+        Ok(freed)
+    })
+}
+
+/// Evicts what no longer fits after the size limit was lowered.
+pub fn enforce_limit() {
+    with_state(|state| {
+        evict(state, None);
+        save_index(state);
+    });
+}
+
+/// Forgets the loaded index so the next access reads the one at the current
+/// cache location. Pending access times have to be flushed before the
+/// location changes, they would end up in the new index otherwise.
+pub fn reload() {
+    *CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+/// Writes pending access times to disk.
+pub fn flush() {
+    with_state(|state| {
+        if state.dirty {
+            save_index(state);
+        }
+    });
+}
@@ -1,20 +1,39 @@
-use crate::utils::appdata;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
 
-pub fn launch_client(version: &str) -> Result<bool, String> {
-    let data = match appdata::return_versions() {
-        Ok(path) => path,
-        Err(_) => return Err("Failed to get the versions directory.".to_string()),
-    };
+use crate::utils::server::{self, DomainMode};
+use crate::utils::settings::{self, WinePrefix};
+use crate::utils::{appdata, manifest, overrides};
+
+const WINE_VARIABLE: &str = "FLUSTER_WINE";
+const WINE_PREFIX_VARIABLE: &str = "FLUSTER_WINE_PREFIX";
+
+const MACOS_WINE_PATHS: [&str; 4] = [
+    "/Applications/Wine Stable.app/Contents/Resources/wine/bin/wine64",
+    "/Applications/Wine Stable.app/Contents/Resources/wine/bin/wine",
+    "/opt/homebrew/bin/wine",
+    "/usr/local/bin/wine",
+];
+
+/// How a client executable gets started on this platform.
+#[derive(Debug, Clone)]
+pub enum Runner {
+    Native,
+    Wine { wine: PathBuf, prefix: PathBuf },
+}
 
+pub fn launch_client(version: &str) -> Result<Child, String> {
     if !is_client_installed(version) {
         return Err("Client is not installed.".to_string());
     }
 
-    std::process::Command::new(data.join(version).join("Roblox.exe"))
-        .spawn()
-        .map_err(|_| "Failed to launch the client.".to_string())?;
+    overrides::set_active_version(version);
+    prepare_base_url(version)?;
 
-    Ok(true)
+    client_command(version, &manifest::cached_launch_args(version))?
+        .spawn()
+        .map_err(|_| "Failed to launch the client.".to_string())
 }
 
 pub fn is_client_installed(version: &str) -> bool {
@@ -58,4 +77,191 @@ pub fn is_client_installed(version: &str) -> bool {
             false
         }
     }
-}
\ No newline at end of file
+}
+
+fn app_settings(base_url: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\r\n<Settings>\r\n\t<ContentFolder>content</ContentFolder>\r\n\t<BaseUrl>{}</BaseUrl>\r\n</Settings>\r\n",
+        base_url
+    )
+}
+
+/// Points the client's `AppSettings.xml` at the local web server when it is
+/// reached by its loopback address, so the client never has to resolve
+/// `www.fluster.is`. In hosts file mode the client is left as it shipped.
+pub fn prepare_base_url(version: &str) -> Result<(), String> {
+    if server::load_server_config().domain_mode != DomainMode::Loopback {
+        return Ok(());
+    }
+
+    let versions = appdata::return_versions()?;
+    let path = versions.join(version).join("AppSettings.xml");
+    let base_url = server::base_url();
+
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => set_base_url(&content, &base_url),
+        Err(_) => app_settings(&base_url),
+    };
+
+    write_if_changed(&path, &content)
+}
+
+fn set_base_url(content: &str, base_url: &str) -> String {
+    let element = format!("<BaseUrl>{}</BaseUrl>", base_url);
+
+    if let (Some(start), Some(end)) = (content.find("<BaseUrl>"), content.find("</BaseUrl>")) {
+        if start < end {
+            return format!(
+                "{}{}{}",
+                &content[..start],
+                element,
+                &content[end + "</BaseUrl>".len()..]
+            );
+        }
+    }
+
+    match content.find("</Settings>") {
+        Some(end) => format!("{}\t{}\r\n{}", &content[..end], element, &content[end..]),
+        None => app_settings(base_url),
+    }
+}
+
+fn write_if_changed(path: &Path, content: &str) -> Result<(), String> {
+    if fs::read_to_string(path).is_ok_and(|current| current == content) {
+        return Ok(());
+    }
+
+    println!("pointing {:?} at the local web server", path);
+
+    fs::write(path, content).map_err(|e| format!("Failed to update {:?}: {}", path, e))
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Finds the Wine binary. `FLUSTER_WINE` wins over the one in the settings,
+/// which wins over the one on `PATH`.
+pub fn detect_wine() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(WINE_VARIABLE)
+        .map(PathBuf::from)
+        .or(settings::current().wine_path)
+    {
+        return path.is_file().then_some(path);
+    }
+
+    ["wine", "wine64"]
+        .iter()
+        .find_map(|name| find_in_path(name))
+        .or_else(|| {
+            MACOS_WINE_PATHS
+                .iter()
+                .filter(|_| cfg!(target_os = "macos"))
+                .map(PathBuf::from)
+                .find(|path| path.is_file())
+        })
+}
+
+/// Every client shares one prefix unless the settings or
+/// `FLUSTER_WINE_PREFIX=version` give each version its own.
+fn wine_prefix(version: &str) -> Result<PathBuf, String> {
+    let wine = appdata::return_appdata()?.join("wine");
+
+    let mode = match std::env::var(WINE_PREFIX_VARIABLE).as_deref() {
+        Ok("version") => WinePrefix::Version,
+        Ok("shared") => WinePrefix::Shared,
+        _ => settings::current().wine_prefix,
+    };
+
+    let prefix = match mode {
+        WinePrefix::Version => wine.join(version),
+        WinePrefix::Shared => wine.join("shared"),
+    };
+
+    // wine sets the prefix itself up on first run, it only needs the parent
+    fs::create_dir_all(&wine)
+        .map_err(|e| format!("Failed to create the Wine directory: {}", e))?;
+
+    Ok(prefix)
+}
+
+pub fn runner(version: &str) -> Result<Runner, String> {
+    if cfg!(target_os = "windows") {
+        return Ok(Runner::Native);
+    }
+
+    let wine = detect_wine().ok_or_else(|| {
+        format!(
+            "Wine is needed to run clients on this platform, install it or point {} at it.",
+            WINE_VARIABLE
+        )
+    })?;
+
+    Ok(Runner::Wine {
+        wine,
+        prefix: wine_prefix(version)?,
+    })
+}
+
+/// Wine maps the host filesystem to `Z:`, so host paths handed to the client,
+/// like place files or `-script` files, are rewritten onto that drive.
+fn translate_arg(arg: &str) -> String {
+    let path = Path::new(arg);
+
+    if path.is_absolute() && path.exists() {
+        format!("Z:{}", arg.replace('/', "\\"))
+    } else {
+        arg.to_string()
+    }
+}
+
+fn wine_log(version: &str) -> Result<File, String> {
+    let logs = appdata::return_appdata()?.join("logs");
+
+    fs::create_dir_all(&logs)
+        .map_err(|e| format!("Failed to create the logs directory: {}", e))?;
+
+    File::create(logs.join(format!("wine-{}.log", version)))
+        .map_err(|e| format!("Failed to create the Wine log: {}", e))
+}
+
+/// Builds the command that starts `version`'s client with `args`, through Wine
+/// where Windows executables can't run natively.
+pub fn client_command(version: &str, args: &[String]) -> Result<Command, String> {
+    let executable = appdata::return_versions()?.join(version).join("Roblox.exe");
+
+    match runner(version)? {
+        Runner::Native => {
+            let mut command = Command::new(executable);
+            command.args(args);
+
+            Ok(command)
+        }
+        Runner::Wine { wine, prefix } => {
+            println!("running {} through {:?} in {:?}", version, wine, prefix);
+
+            let log = wine_log(version)?;
+            let output = log
+                .try_clone()
+                .map_err(|e| format!("Failed to open the Wine log: {}", e))?;
+
+            let mut command = Command::new(wine);
+            command
+                .env("WINEPREFIX", prefix)
+                .arg(&executable)
+                .args(args.iter().map(|arg| translate_arg(arg)))
+                .stdout(output)
+                .stderr(log);
+
+            if let Some(directory) = executable.parent() {
+                command.current_dir(directory);
+            }
+
+            Ok(command)
+        }
+    }
+}
@@ -0,0 +1,495 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub const CANCELLED: &str = "The download was cancelled";
+
+pub fn part_path(destination: &Path) -> PathBuf {
+    let mut name = destination
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+
+    name.push(".part");
+    destination.with_file_name(name)
+}
+
+fn total_from_content_range(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit('/')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Downloads `url` into `destination`, streaming the body into a `.part` file
+/// next to it and resuming with an HTTP Range request whenever the connection
+/// drops. The `.part` file survives restarts, so a later call picks up where
+/// the previous one stopped.
+///
+/// `on_progress` is called with the bytes written so far and, when the server
+/// reports it, the total size of the file. Setting `cancel` stops the download
+/// after the current chunk and leaves the `.part` file for the caller to keep
+/// or discard.
+pub async fn download_resumable(
+    url: &str,
+    destination: &Path,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<u64, String> {
+    let part = part_path(destination);
+
+    let client = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create the download client: {}", e))?;
+
+    let mut failures = 0;
+    let mut last_error = String::new();
+    let mut resumed = false;
+
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(CANCELLED.to_string());
+        }
+
+        if failures >= MAX_ATTEMPTS {
+            return Err(format!(
+                "Failed to download after {} attempts: {}",
+                MAX_ATTEMPTS, last_error
+            ));
+        }
+
+        if failures > 0 {
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+
+        let offset = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+
+        let mut request = client.get(url);
+        if offset > 0 {
+            println!("resuming download of {} from byte {}", url, offset);
+            request = request.header(RANGE, format!("bytes={}-", offset));
+        }
+
+        let mut response = match request.send().await {
+            Ok(response) => response,
+            // only retry a server that already sent part of the file, so callers
+            // with more than one source can move on to the next one quickly
+            Err(e) if !resumed => return Err(format!("Failed to contact the server: {}", e)),
+            Err(e) => {
+                last_error = format!("Failed to contact the server: {}", e);
+                failures += 1;
+                continue;
+            }
+        };
+
+        println!("download response status: {}", response.status());
+
+        let (mut file, mut written, total) = match response.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                let file = OpenOptions::new()
+                    .append(true)
+                    .open(&part)
+                    .map_err(|e| format!("Failed to open the partial download: {}", e))?;
+
+                let total = total_from_content_range(&response)
+                    .or_else(|| response.content_length().map(|len| len + offset));
+
+                (file, offset, total)
+            }
+            StatusCode::OK => {
+                // the server ignored the range, so start over from the beginning
+                let file = File::create(&part)
+                    .map_err(|e| format!("Failed to create the destination file: {}", e))?;
+
+                (file, 0, response.content_length())
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                if total_from_content_range(&response) == Some(offset) {
+                    break;
+                }
+
+                fs::remove_file(&part)
+                    .map_err(|e| format!("Failed to discard the partial download: {}", e))?;
+                last_error = "The server rejected the partial download".to_string();
+                failures += 1;
+                continue;
+            }
+            StatusCode::NOT_FOUND => {
+                return Err("The file was not found on the server".to_string());
+            }
+            status if status.is_server_error() && !resumed => {
+                return Err(format!("Server responded with error code {}", status));
+            }
+            status if status.is_server_error() => {
+                last_error = format!("Server responded with error code {}", status);
+                failures += 1;
+                continue;
+            }
+            status => {
+                return Err(format!("Server responded with error code {}", status));
+            }
+        };
+
+        let mut progressed = false;
+        let mut interrupted = None;
+
+        on_progress(written, total);
+
+        loop {
+            if cancel.load(Ordering::Relaxed) {
+                file.flush()
+                    .map_err(|e| format!("Failed to write the download to disk: {}", e))?;
+                return Err(CANCELLED.to_string());
+            }
+
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    file.write_all(&chunk)
+                        .map_err(|e| format!("Failed to write the download to disk: {}", e))?;
+                    written += chunk.len() as u64;
+                    progressed = true;
+
+                    on_progress(written, total);
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    interrupted = Some(format!("The connection was interrupted: {}", e));
+                    break;
+                }
+            }
+        }
+
+        file.flush()
+            .map_err(|e| format!("Failed to write the download to disk: {}", e))?;
+
+        if progressed {
+            failures = 0;
+            resumed = true;
+        }
+
+        if let Some(e) = interrupted {
+            println!("download interrupted at byte {}: {}", written, e);
+            last_error = e;
+            failures += 1;
+            continue;
+        }
+
+        match total {
+            Some(total) if written < total => {
+                last_error = format!("The download ended early ({} of {} bytes)", written, total);
+                failures += 1;
+                continue;
+            }
+            _ => break,
+        }
+    }
+
+    fs::rename(&part, destination)
+        .map_err(|e| format!("Failed to finalize the download: {}", e))?;
+
+    let size = fs::metadata(destination)
+        .map(|m| m.len())
+        .map_err(|e| format!("Failed to read the downloaded file: {}", e))?;
+
+    println!("downloaded {} bytes to {:?}", size, destination);
+
+    Ok(size)
+}
+
+/// Copies a build from a `file://` mirror such as a network share or a USB
+/// stick, going through a `.part` file like HTTP downloads do.
+pub async fn copy_local(
+    source: &Path,
+    destination: &Path,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<u64, String> {
+    let part = part_path(destination);
+
+    let mut input = File::open(source).map_err(|e| format!("Failed to open {:?}: {}", source, e))?;
+    let total = input.metadata().map(|m| m.len()).ok();
+
+    let mut output =
+        File::create(&part).map_err(|e| format!("Failed to create the destination file: {}", e))?;
+
+    let mut buffer = vec![0; 64 * 1024];
+    let mut written: u64 = 0;
+
+    on_progress(written, total);
+
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(CANCELLED.to_string());
+        }
+
+        let read = input
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {:?}: {}", source, e))?;
+
+        if read == 0 {
+            break;
+        }
+
+        output
+            .write_all(&buffer[..read])
+            .map_err(|e| format!("Failed to write the download to disk: {}", e))?;
+
+        written += read as u64;
+        on_progress(written, total);
+
+        // local copies never wait on the network, give the runtime a chance to
+        // run other tasks between chunks
+        tokio::task::yield_now().await;
+    }
+
+    output
+        .flush()
+        .map_err(|e| format!("Failed to write the download to disk: {}", e))?;
+    drop(output);
+
+    fs::rename(&part, destination).map_err(|e| format!("Failed to finalize the download: {}", e))?;
+
+    Ok(written)
+}
+
+/// Fetches `url` into `destination`, either over HTTP or from a `file://` URL.
+pub async fn download(
+    url: &str,
+    destination: &Path,
+    cancel: &AtomicBool,
+    on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<u64, String> {
+    if url.starts_with("file:") {
+        let source = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .ok_or_else(|| format!("{} is not a valid local path", url))?;
+
+        return copy_local(&source, destination, cancel, on_progress).await;
+    }
+
+    download_resumable(url, destination, cancel, on_progress).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn body() -> Vec<u8> {
+        (0..200_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn response(status: &str, headers: &[String], body: &[u8]) -> Vec<u8> {
+        let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+
+        for header in headers {
+            response.push_str(header);
+            response.push_str("\r\n");
+        }
+
+        response.push_str("\r\n");
+
+        let mut response = response.into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    /// A web server that answers every connection with what `handler` returns
+    /// for it and hangs up, recording the Range offset each request asked for.
+    async fn stand_in(
+        handler: impl Fn(usize, Option<u64>) -> Vec<u8> + Send + 'static,
+    ) -> (String, Arc<Mutex<Vec<Option<u64>>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/client.zip", listener.local_addr().unwrap());
+
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let seen = ranges.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match socket.read(&mut buffer).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => request.extend_from_slice(&buffer[..read]),
+                    }
+                }
+
+                let range = String::from_utf8_lossy(&request)
+                    .to_lowercase()
+                    .lines()
+                    .find_map(|line| line.strip_prefix("range: bytes=")?.strip_suffix('-')?.parse().ok());
+
+                let index = {
+                    let mut seen = seen.lock().unwrap();
+                    seen.push(range);
+                    seen.len() - 1
+                };
+
+                let _ = socket.write_all(&handler(index, range)).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        (url, ranges)
+    }
+
+    fn destination(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fluster-download-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("client.zip")
+    }
+
+    /// The first response promises the whole body but the connection drops
+    /// halfway through it.
+    fn dropped_halfway(body: &[u8]) -> Vec<u8> {
+        response(
+            "200 OK",
+            &[format!("Content-Length: {}", body.len())],
+            &body[..body.len() / 2],
+        )
+    }
+
+    #[tokio::test]
+    async fn resumes_with_a_range_request_after_a_drop() {
+        let (url, ranges) = stand_in(|index, range| {
+            let body = body();
+
+            match (index, range) {
+                (0, None) => dropped_halfway(&body),
+                (_, Some(offset)) => {
+                    let offset = offset as usize;
+                    response(
+                        "206 Partial Content",
+                        &[
+                            format!("Content-Length: {}", body.len() - offset),
+                            format!("Content-Range: bytes {}-{}/{}", offset, body.len() - 1, body.len()),
+                        ],
+                        &body[offset..],
+                    )
+                }
+                _ => response("500 Internal Server Error", &[], b""),
+            }
+        })
+        .await;
+
+        let destination = destination("resume");
+        let size = download_resumable(&url, &destination, &AtomicBool::new(false), |_, _| {})
+            .await
+            .unwrap();
+
+        assert_eq!(size, body().len() as u64);
+        assert_eq!(fs::read(&destination).unwrap(), body());
+        assert!(!part_path(&destination).exists());
+
+        let ranges = ranges.lock().unwrap();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0], None);
+        assert!(ranges[1].is_some_and(|offset| offset > 0 && offset < body().len() as u64));
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_range_is_ignored() {
+        let (url, ranges) = stand_in(|index, _| {
+            let body = body();
+
+            if index == 0 {
+                dropped_halfway(&body)
+            } else {
+                response("200 OK", &[format!("Content-Length: {}", body.len())], &body)
+            }
+        })
+        .await;
+
+        let destination = destination("ignored");
+        let size = download_resumable(&url, &destination, &AtomicBool::new(false), |_, _| {})
+            .await
+            .unwrap();
+
+        // a resumed file that got the whole body appended would be too long
+        assert_eq!(size, body().len() as u64);
+        assert_eq!(fs::read(&destination).unwrap(), body());
+        assert!(ranges.lock().unwrap()[1].is_some());
+    }
+
+    #[tokio::test]
+    async fn finishes_when_the_part_file_is_already_complete() {
+        let total = body().len();
+
+        let (url, ranges) = stand_in(move |_, _| {
+            response(
+                "416 Range Not Satisfiable",
+                &[format!("Content-Range: bytes */{}", total), "Content-Length: 0".to_string()],
+                b"",
+            )
+        })
+        .await;
+
+        let destination = destination("complete");
+        fs::write(part_path(&destination), body()).unwrap();
+
+        let size = download_resumable(&url, &destination, &AtomicBool::new(false), |_, _| {})
+            .await
+            .unwrap();
+
+        assert_eq!(size, total as u64);
+        assert_eq!(fs::read(&destination).unwrap(), body());
+        assert_eq!(*ranges.lock().unwrap(), vec![Some(total as u64)]);
+    }
+
+    #[tokio::test]
+    async fn discards_a_part_file_the_server_rejects() {
+        let (url, ranges) = stand_in(|_, range| {
+            let body = body();
+
+            match range {
+                Some(_) => response(
+                    "416 Range Not Satisfiable",
+                    &[format!("Content-Range: bytes */{}", body.len()), "Content-Length: 0".to_string()],
+                    b"",
+                ),
+                None => response("200 OK", &[format!("Content-Length: {}", body.len())], &body),
+            }
+        })
+        .await;
+
+        let destination = destination("rejected");
+
+        // left over from a different, bigger build
+        fs::write(part_path(&destination), vec![7; body().len() + 10]).unwrap();
+
+        download_resumable(&url, &destination, &AtomicBool::new(false), |_, _| {})
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&destination).unwrap(), body());
+        assert_eq!(*ranges.lock().unwrap(), vec![Some(body().len() as u64 + 10), None]);
+    }
+}
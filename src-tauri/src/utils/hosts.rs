@@ -0,0 +1,311 @@
+use std::fs;
+use std::net::{IpAddr, ToSocketAddrs};
+
+use serde::Serialize;
+
+use crate::utils::server;
+
+const BEGIN_MARKER: &str = "# BEGIN Fluster Local Domain Entries";
+const END_MARKER: &str = "# END Fluster Local Domain Entries";
+
+/// Header of the unmarked block older releases appended.
+const LEGACY_HEADER: &str = "# Fluster Local Domain Entries";
+
+const DOMAINS: [&str; 2] = ["fluster.is", "www.fluster.is"];
+
+#[derive(Debug, Serialize)]
+pub struct DomainResolution {
+    pub domain: String,
+    pub addresses: Vec<String>,
+    pub points_to_local: bool,
+    pub error: Option<String>,
+}
+
+pub fn hosts_path() -> &'static str {
+    if cfg!(target_os = "windows") {
+        r"C:\Windows\System32\drivers\etc\hosts"
+    } else {
+        "/etc/hosts"
+    }
+}
+
+fn line_ending(contents: &str) -> &'static str {
+    if contents.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+fn entry_lines() -> Vec<String> {
+    DOMAINS
+        .iter()
+        .map(|domain| format!("127.0.0.1 {}", domain))
+        .collect()
+}
+
+fn normalize(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A hosts file with the Fluster lines taken out.
+struct Split<'a> {
+    kept: Vec<&'a str>,
+    /// The entries between the markers of a block that was closed.
+    block_entries: Vec<String>,
+    /// Whether any Fluster line was there, including a legacy block.
+    found: bool,
+}
+
+/// Only ever drops the lines Fluster writes, so a block that lost its end
+/// marker doesn't take the rest of the file with it.
+fn split_block(contents: &str) -> Split<'_> {
+    let expected = entry_lines();
+
+    let mut split = Split {
+        kept: Vec::new(),
+        block_entries: Vec::new(),
+        found: false,
+    };
+
+    let mut block: Option<Vec<String>> = None;
+
+    for line in contents.lines() {
+        let normalized = normalize(line);
+
+        if normalized == BEGIN_MARKER {
+            block = Some(Vec::new());
+            split.found = true;
+        } else if normalized == END_MARKER {
+            if let Some(entries) = block.take() {
+                split.block_entries.extend(entries);
+            }
+            split.found = true;
+        } else if normalized == LEGACY_HEADER || expected.contains(&normalized) {
+            if let Some(entries) = block.as_mut() {
+                entries.push(normalized);
+            }
+            split.found = true;
+        } else {
+            split.kept.push(line);
+        }
+    }
+
+    // the block is appended after a blank line, don't leave it behind
+    while split.kept.last().is_some_and(|line| line.trim().is_empty()) {
+        split.kept.pop();
+    }
+
+    split
+}
+
+/// Whether `contents` already maps every Fluster domain inside the block,
+/// however the file's lines end or the block is ordered.
+pub fn has_entries(contents: &str) -> bool {
+    let split = split_block(contents);
+
+    entry_lines()
+        .iter()
+        .all(|line| split.block_entries.contains(line))
+}
+
+/// Whether `contents` holds any Fluster entries, including a legacy block.
+pub fn contains_entries(contents: &str) -> bool {
+    split_block(contents).found
+}
+
+pub fn add_entries(contents: &str) -> String {
+    let newline = line_ending(contents);
+    let kept = split_block(contents).kept;
+
+    let mut lines: Vec<String> = kept.into_iter().map(|line| line.to_string()).collect();
+
+    if !lines.is_empty() {
+        lines.push(String::new());
+    }
+
+    lines.push(BEGIN_MARKER.to_string());
+    lines.extend(entry_lines());
+    lines.push(END_MARKER.to_string());
+
+    lines.join(newline) + newline
+}
+
+pub fn remove_entries(contents: &str) -> String {
+    let newline = line_ending(contents);
+    let kept = split_block(contents).kept;
+
+    if kept.is_empty() {
+        return String::new();
+    }
+
+    kept.join(newline) + newline
+}
+
+fn update_hosts_file(update: impl FnOnce(&str) -> String) -> Result<bool, String> {
+    let path = hosts_path();
+
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read hosts file: {}", e))?;
+
+    let updated = update(&contents);
+
+    if updated == contents {
+        return Ok(false);
+    }
+
+    fs::write(path, updated).map_err(|e| match e.kind() {
+        std::io::ErrorKind::PermissionDenied => {
+            "Permission denied to write to hosts file. Please run with sudo.".to_string()
+        }
+        _ => format!("Failed to write to hosts file: {}", e),
+    })?;
+
+    Ok(true)
+}
+
+/// Adds or refreshes the Fluster block, returns whether the file changed.
+pub fn write_hosts_file() -> Result<bool, String> {
+    update_hosts_file(|contents| {
+        if has_entries(contents) {
+            contents.to_string()
+        } else {
+            add_entries(contents)
+        }
+    })
+}
+
+/// Removes the Fluster block, returns whether the file changed.
+pub fn clean_hosts_file() -> Result<bool, String> {
+    update_hosts_file(|contents| {
+        if contains_entries(contents) {
+            remove_entries(contents)
+        } else {
+            contents.to_string()
+        }
+    })
+}
+
+pub fn hosts_file_has_entries() -> Result<bool, String> {
+    let contents = fs::read_to_string(hosts_path())
+        .map_err(|e| format!("Failed to read hosts file: {}", e))?;
+
+    Ok(has_entries(&contents))
+}
+
+fn is_local_server(address: IpAddr) -> bool {
+    let config = server::load_server_config();
+
+    match config.address.parse::<IpAddr>() {
+        Ok(server_address) if server_address.is_unspecified() || server_address.is_loopback() => {
+            address.is_loopback()
+        }
+        Ok(server_address) => address == server_address,
+        Err(_) => address.is_loopback(),
+    }
+}
+
+/// Looks `www.fluster.is` up the way the clients would and checks it lands on
+/// the address the local web server listens on.
+pub fn check_resolution() -> DomainResolution {
+    let domain = DOMAINS[1];
+
+    match (domain, 80).to_socket_addrs() {
+        Ok(addresses) => {
+            let addresses: Vec<IpAddr> = addresses.map(|address| address.ip()).collect();
+
+            DomainResolution {
+                domain: domain.to_string(),
+                points_to_local: !addresses.is_empty()
+                    && addresses.iter().all(|address| is_local_server(*address)),
+                addresses: addresses.iter().map(|address| address.to_string()).collect(),
+                error: None,
+            }
+        }
+        Err(e) => DomainResolution {
+            domain: domain.to_string(),
+            addresses: Vec::new(),
+            points_to_local: false,
+            error: Some(format!("Failed to resolve {}: {}", domain, e)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS: &str = "127.0.0.1 localhost\n::1 localhost\n";
+
+    #[test]
+    fn adding_then_removing_restores_the_file() {
+        let added = add_entries(HOSTS);
+
+        assert!(has_entries(&added));
+        assert!(contains_entries(&added));
+        assert_eq!(remove_entries(&added), HOSTS);
+    }
+
+    #[test]
+    fn keeps_crlf_line_endings() {
+        let original = HOSTS.replace('\n', "\r\n");
+        let added = add_entries(&original);
+
+        assert!(added.ends_with(&format!("{}\r\n", END_MARKER)));
+        assert!(!added.replace("\r\n", "").contains('\n'));
+        assert!(has_entries(&added));
+        assert_eq!(remove_entries(&added), original);
+    }
+
+    #[test]
+    fn accepts_a_reordered_block() {
+        let contents = format!(
+            "{}\n{}\n127.0.0.1   www.fluster.is\n127.0.0.1\tfluster.is\n{}\n",
+            HOSTS.trim_end(),
+            BEGIN_MARKER,
+            END_MARKER
+        );
+
+        assert!(has_entries(&contents));
+        assert_eq!(remove_entries(&contents), HOSTS);
+    }
+
+    #[test]
+    fn removes_the_legacy_block() {
+        let contents = format!(
+            "{}\n\n{}\n127.0.0.1 fluster.is\n127.0.0.1 www.fluster.is\n",
+            HOSTS.trim_end(),
+            LEGACY_HEADER
+        );
+
+        assert!(!has_entries(&contents));
+        assert!(contains_entries(&contents));
+        assert_eq!(remove_entries(&contents), HOSTS);
+        assert_eq!(remove_entries(&add_entries(&contents)), HOSTS);
+    }
+
+    #[test]
+    fn keeps_lines_after_a_block_without_an_end_marker() {
+        let contents = format!(
+            "{}\n127.0.0.1 fluster.is\n10.0.0.2 nas.local\n127.0.0.1 www.fluster.is\n192.168.1.5 printer\n",
+            BEGIN_MARKER
+        );
+
+        assert!(!has_entries(&contents));
+        assert!(contains_entries(&contents));
+        assert_eq!(remove_entries(&contents), "10.0.0.2 nas.local\n192.168.1.5 printer\n");
+
+        let added = add_entries(&contents);
+
+        assert!(has_entries(&added));
+        assert!(added.contains("10.0.0.2 nas.local"));
+        assert!(added.contains("192.168.1.5 printer"));
+    }
+
+    #[test]
+    fn leaves_files_without_entries_alone() {
+        assert!(!contains_entries(HOSTS));
+        assert!(!has_entries(HOSTS));
+        assert_eq!(remove_entries(HOSTS), HOSTS);
+    }
+}
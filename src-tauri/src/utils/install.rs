@@ -0,0 +1,442 @@
+use std::fs::{self, File};
+use std::io::{copy, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use zip::ZipArchive;
+
+use crate::utils::appdata;
+
+pub const CANCELLED: &str = "The installation was cancelled";
+
+/// Upper bound for everything an archive may unpack to, no client build comes
+/// anywhere close to this so anything bigger is treated as a zip bomb.
+pub const MAX_UNCOMPRESSED_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Versions are extracted next to their final location first, so a failed or
+/// cancelled install never leaves a half-populated `versions/<version>` behind.
+pub fn staging_path(versions: &Path, version: &str) -> PathBuf {
+    versions.join(format!("{}.staging", version))
+}
+
+fn is_unsafe_name(name: &str) -> bool {
+    // enclosed_name only understands the separators of the host platform, so
+    // backslash traversal and drive letters are checked separately, including
+    // drive relative names like `C:file`
+    let mut bytes = name.bytes();
+    let has_drive = matches!(
+        (bytes.next(), bytes.next()),
+        (Some(letter), Some(b':')) if letter.is_ascii_alphabetic()
+    );
+
+    has_drive || name.split(['/', '\\']).any(|component| component == "..")
+}
+
+/// Checks every entry before anything is written, rejecting links, absolute
+/// paths and names escaping the destination, as well as archives that would
+/// unpack past [`MAX_UNCOMPRESSED_SIZE`]. Returns the total uncompressed size.
+pub fn validate_archive<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<u64, String> {
+    validate_archive_within(archive, MAX_UNCOMPRESSED_SIZE)
+}
+
+fn validate_archive_within<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    limit: u64,
+) -> Result<u64, String> {
+    let mut rejected = Vec::new();
+    let mut total_size: u64 = 0;
+
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| format!("Failed to read the zip entry: {}", e))?;
+
+        if entry.is_symlink() {
+            rejected.push(format!("{} (symbolic link)", entry.name()));
+        } else if entry.enclosed_name().is_none() || is_unsafe_name(entry.name()) {
+            rejected.push(format!("{} (path escapes the install directory)", entry.name()));
+        }
+
+        total_size = total_size.saturating_add(entry.size());
+    }
+
+    if !rejected.is_empty() {
+        return Err(format!(
+            "The archive contains unsafe entries: {}",
+            rejected.join(", ")
+        ));
+    }
+
+    if total_size > limit {
+        return Err(format!(
+            "The archive would unpack to {} bytes, more than the allowed {} bytes",
+            total_size, limit
+        ));
+    }
+
+    Ok(total_size)
+}
+
+/// Copies an entry as long as the archive stays within `remaining` bytes, the
+/// sizes in the headers can lie so this is what actually holds it to `limit`.
+pub fn copy_capped(
+    entry: &mut impl Read,
+    writer: &mut impl Write,
+    remaining: &mut u64,
+    limit: u64,
+) -> Result<u64, String> {
+    let copied = copy(&mut entry.take(*remaining + 1), writer)
+        .map_err(|e| format!("Failed to unpack the file: {}", e))?;
+
+    if copied > *remaining {
+        return Err(format!(
+            "The archive unpacks to more than the allowed {} bytes",
+            limit
+        ));
+    }
+
+    *remaining -= copied;
+
+    Ok(copied)
+}
+
+pub fn extract_archive<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    destination: &Path,
+    cancel: &AtomicBool,
+    on_entry: impl FnMut(&str, usize, usize),
+) -> Result<(), String> {
+    extract_archive_within(archive, destination, cancel, MAX_UNCOMPRESSED_SIZE, on_entry)
+}
+
+fn extract_archive_within<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    destination: &Path,
+    cancel: &AtomicBool,
+    limit: u64,
+    mut on_entry: impl FnMut(&str, usize, usize),
+) -> Result<(), String> {
+    validate_archive_within(archive, limit)?;
+
+    fs::create_dir_all(destination)
+        .map_err(|e| format!("Failed to create the staging directory: {}", e))?;
+
+    let entry_count = archive.len();
+    let mut remaining = limit;
+
+    for i in 0..entry_count {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(CANCELLED.to_string());
+        }
+
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to read the zip entry: {}", e))?;
+
+        let enclosed_name = entry
+            .enclosed_name()
+            .ok_or_else(|| format!("The archive contains an unsafe entry: {}", entry.name()))?;
+
+        let out_path = destination.join(enclosed_name);
+        println!("extracting: {} to {:?}", entry.name(), out_path);
+
+        on_entry(entry.name(), i + 1, entry_count);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path)
+                .map_err(|e| format!("Failed to create the directory: {}", e))?;
+        } else {
+            if let Some(p) = out_path.parent() {
+                if !p.exists() {
+                    fs::create_dir_all(p)
+                        .map_err(|e| format!("Failed to create the parent directory: {}", e))?;
+                }
+            }
+
+            let mut outfile =
+                File::create(&out_path).map_err(|e| format!("Failed to create the file: {}", e))?;
+
+            copy_capped(&mut entry, &mut outfile, &mut remaining, limit)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_entries(dir: &Path, entries: &mut Vec<PathBuf>) -> Result<(), String> {
+    let read_dir = fs::read_dir(dir).map_err(|e| format!("Failed to read {:?}: {}", dir, e))?;
+
+    for entry in read_dir {
+        let path = entry
+            .map_err(|e| format!("Failed to read {:?}: {}", dir, e))?
+            .path();
+
+        let metadata = fs::symlink_metadata(&path)
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+
+        if metadata.file_type().is_symlink() {
+            return Err(format!("{:?} is a symbolic link", path));
+        }
+
+        entries.push(path.clone());
+
+        if metadata.is_dir() {
+            collect_entries(&path, entries)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies an already extracted client folder, with the same size limit and
+/// link restrictions archives get.
+pub fn copy_directory(
+    source: &Path,
+    destination: &Path,
+    cancel: &AtomicBool,
+    mut on_entry: impl FnMut(&str, usize, usize),
+) -> Result<(), String> {
+    let mut entries = Vec::new();
+    collect_entries(source, &mut entries)?;
+
+    let total_size: u64 = entries
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum();
+
+    if total_size > MAX_UNCOMPRESSED_SIZE {
+        return Err(format!(
+            "The folder holds {} bytes, more than the allowed {} bytes",
+            total_size, MAX_UNCOMPRESSED_SIZE
+        ));
+    }
+
+    fs::create_dir_all(destination)
+        .map_err(|e| format!("Failed to create the staging directory: {}", e))?;
+
+    let entry_count = entries.len();
+
+    for (i, path) in entries.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(CANCELLED.to_string());
+        }
+
+        let relative = path
+            .strip_prefix(source)
+            .map_err(|_| format!("{:?} is outside of {:?}", path, source))?;
+
+        let out_path = destination.join(relative);
+
+        on_entry(&relative.to_string_lossy(), i + 1, entry_count);
+
+        if path.is_dir() {
+            fs::create_dir_all(&out_path)
+                .map_err(|e| format!("Failed to create the directory: {}", e))?;
+        } else {
+            fs::copy(path, &out_path).map_err(|e| format!("Failed to copy the file: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn has_client_executable(path: &Path) -> bool {
+    fs::metadata(path.join("Roblox.exe"))
+        .map(|metadata| metadata.is_file() && metadata.len() > 0)
+        .unwrap_or(false)
+}
+
+/// Moves a fully extracted staging directory into `target`, replacing any
+/// leftovers from an earlier broken install.
+pub fn commit_staging(staging: &Path, target: &Path) -> Result<(), String> {
+    // whatever is at the target gets deleted, so it has to be a version folder
+    let in_versions = target
+        .parent()
+        .and_then(Path::file_name)
+        .is_some_and(|parent| parent == "versions");
+
+    let is_version = target
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| appdata::validate_version(name).is_ok());
+
+    if !in_versions || !is_version {
+        return Err(format!("{:?} is not a version directory", target));
+    }
+
+    if target.exists() {
+        fs::remove_dir_all(target)
+            .map_err(|e| format!("Failed to remove the previous installation: {}", e))?;
+    }
+
+    fs::rename(staging, target)
+        .map_err(|e| format!("Failed to move the installation into place: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    use super::*;
+
+    fn build_zip(build: impl FnOnce(&mut ZipWriter<Cursor<Vec<u8>>>)) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        build(&mut writer);
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn zip_with_files(names: &[&str]) -> Vec<u8> {
+        build_zip(|writer| {
+            for name in names {
+                writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+                writer.write_all(b"payload").unwrap();
+            }
+        })
+    }
+
+    fn open(bytes: Vec<u8>) -> ZipArchive<Cursor<Vec<u8>>> {
+        ZipArchive::new(Cursor::new(bytes)).unwrap()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fluster-install-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn assert_rejected(names: &[&str]) {
+        let error = validate_archive(&mut open(zip_with_files(names))).unwrap_err();
+
+        for name in names {
+            assert!(error.contains(name), "{} is missing from: {}", name, error);
+        }
+    }
+
+    #[test]
+    fn accepts_plain_entries() {
+        let bytes = zip_with_files(&["Roblox.exe", "content/fonts/arial.ttf"]);
+        let destination = temp_dir("plain");
+
+        extract_archive(&mut open(bytes), &destination, &AtomicBool::new(false), |_, _, _| {}).unwrap();
+
+        assert!(destination.join("content/fonts/arial.ttf").is_file());
+        fs::remove_dir_all(destination).unwrap();
+    }
+
+    #[test]
+    fn rejects_parent_traversal() {
+        assert_rejected(&["../evil.txt"]);
+        assert_rejected(&["content/../../evil.txt"]);
+    }
+
+    #[test]
+    fn rejects_absolute_and_drive_paths() {
+        assert_rejected(&["/etc/evil.txt"]);
+        assert_rejected(&["C:/Windows/evil.txt"]);
+        assert_rejected(&["C:evil.txt"]);
+    }
+
+    #[test]
+    fn rejects_backslash_traversal() {
+        assert_rejected(&["..\\evil.txt"]);
+        assert_rejected(&["content\\..\\..\\evil.txt"]);
+    }
+
+    #[test]
+    fn rejects_symlinks() {
+        let bytes = build_zip(|writer| {
+            writer
+                .add_symlink("link", "/etc/passwd", SimpleFileOptions::default())
+                .unwrap();
+        });
+
+        let error = validate_archive(&mut open(bytes)).unwrap_err();
+        assert!(error.contains("link (symbolic link)"), "{}", error);
+    }
+
+    #[test]
+    fn names_every_rejected_entry() {
+        let names = ["../one.txt", "/two.txt", "C:/three.txt", "..\\four.txt"];
+
+        let bytes = build_zip(|writer| {
+            writer.start_file("Roblox.exe", SimpleFileOptions::default()).unwrap();
+
+            for name in names {
+                writer.start_file(name, SimpleFileOptions::default()).unwrap();
+            }
+
+            writer
+                .add_symlink("five", "../../outside", SimpleFileOptions::default())
+                .unwrap();
+        });
+
+        let error = validate_archive(&mut open(bytes)).unwrap_err();
+
+        for name in names.iter().chain(&["five"]) {
+            assert!(error.contains(name), "{} is missing from: {}", name, error);
+        }
+
+        assert!(!error.contains("Roblox.exe"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_archives_over_the_limit() {
+        let bytes = build_zip(|writer| {
+            writer.start_file("big.bin", SimpleFileOptions::default()).unwrap();
+            writer.write_all(&[0; 4096]).unwrap();
+        });
+
+        assert!(validate_archive_within(&mut open(bytes), 1024).is_err());
+    }
+
+    /// Rewrites the uncompressed size in the local and central headers, the
+    /// way a zip bomb hides how much it really unpacks to.
+    fn understate_size(bytes: &mut [u8], size: u32) {
+        let mut i = 0;
+
+        while i + 4 <= bytes.len() {
+            let offset = match bytes[i..i + 4] {
+                [0x50, 0x4b, 0x03, 0x04] => Some(22),
+                [0x50, 0x4b, 0x01, 0x02] => Some(24),
+                _ => None,
+            };
+
+            if let Some(offset) = offset {
+                bytes[i + offset..i + offset + 4].copy_from_slice(&size.to_le_bytes());
+            }
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn caps_entries_that_understate_their_size() {
+        let mut bytes = build_zip(|writer| {
+            let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+            writer.start_file("bomb.bin", options).unwrap();
+            writer.write_all(&[0; 64 * 1024]).unwrap();
+        });
+
+        understate_size(&mut bytes, 16);
+
+        let mut archive = open(bytes);
+        assert_eq!(validate_archive_within(&mut archive, 1024).unwrap(), 16);
+
+        let destination = temp_dir("bomb");
+        let error =
+            extract_archive_within(&mut archive, &destination, &AtomicBool::new(false), 1024, |_, _, _| {})
+                .unwrap_err();
+
+        assert!(error.contains("more than the allowed 1024 bytes"), "{}", error);
+
+        let written = fs::metadata(destination.join("bomb.bin")).unwrap().len();
+        assert!(written <= 1025, "{} bytes were written", written);
+
+        fs::remove_dir_all(destination).unwrap();
+    }
+}
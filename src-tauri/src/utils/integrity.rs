@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{copy, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zip::ZipArchive;
+
+use crate::utils::install;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileRecord {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileManifest {
+    pub version: String,
+    /// Set for builds imported without a matching entry in the version manifest.
+    #[serde(default)]
+    pub unverified: bool,
+    pub files: Vec<FileRecord>,
+}
+
+/// Files Fluster rewrites itself, like the `AppSettings.xml` loopback mode
+/// points at the local web server. They are left out of the file records so
+/// they never show up as modified and a repair doesn't undo them.
+const MANAGED_FILES: &[&str] = &["AppSettings.xml"];
+
+fn is_managed(path: &str) -> bool {
+    MANAGED_FILES
+        .iter()
+        .any(|managed| managed.eq_ignore_ascii_case(path))
+}
+
+/// An installed version with a recorded file list.
+#[derive(Debug, Serialize)]
+pub struct RecordedVersion {
+    pub id: String,
+    pub unverified: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VerifyReport {
+    pub missing: Vec<String>,
+    pub modified: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+/// The file list lives next to the install rather than inside it, so it never
+/// shows up as an extra file itself.
+pub fn file_manifest_path(versions: &Path, version: &str) -> PathBuf {
+    versions.join(format!("{}.files.json", version))
+}
+
+fn hash_reader(reader: &mut impl Read) -> std::io::Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let size = copy(reader, &mut hasher)?;
+
+    Ok((size, hex::encode(hasher.finalize())))
+}
+
+fn hash_file(path: &Path) -> std::io::Result<(u64, String)> {
+    hash_reader(&mut File::open(path)?)
+}
+
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+
+    let components: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    Some(components.join("/"))
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn build_file_records(root: &Path) -> Result<Vec<FileRecord>, String> {
+    let mut files = Vec::new();
+    collect_files(root, &mut files)
+        .map_err(|e| format!("Failed to list the installed files: {}", e))?;
+
+    let mut records = Vec::with_capacity(files.len());
+
+    for file in files {
+        let Some(path) = relative_path(root, &file).filter(|path| !is_managed(path)) else {
+            continue;
+        };
+
+        let (size, sha256) =
+            hash_file(&file).map_err(|e| format!("Failed to hash {}: {}", path, e))?;
+
+        records.push(FileRecord { path, size, sha256 });
+    }
+
+    records.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(records)
+}
+
+pub fn save_file_manifest(path: &Path, manifest: &FileManifest) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize the file manifest: {}", e))?;
+
+    // swapped in whole, a crash mid-write leaves the previous list behind
+    let temp_path = path.with_extension("json.tmp");
+
+    File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path))
+        .map_err(|e| format!("Failed to write the file manifest: {}", e))
+}
+
+pub fn load_file_manifest(path: &Path) -> Result<FileManifest, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read the file manifest: {}", e))?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse the file manifest: {}", e))
+}
+
+/// Every installed version that has a file list, including imported builds
+/// the version manifest doesn't know about.
+pub fn recorded_versions(versions: &Path) -> Vec<RecordedVersion> {
+    let Ok(entries) = fs::read_dir(versions) else {
+        return Vec::new();
+    };
+
+    let mut recorded: Vec<RecordedVersion> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            let id = file_name.to_str()?.strip_suffix(".files.json")?;
+
+            if !versions.join(id).is_dir() {
+                return None;
+            }
+
+            let manifest = load_file_manifest(&entry.path()).ok()?;
+
+            Some(RecordedVersion {
+                id: id.to_string(),
+                unverified: manifest.unverified,
+            })
+        })
+        .collect();
+
+    recorded.sort_by(|a, b| a.id.cmp(&b.id));
+
+    recorded
+}
+
+pub fn verify_files(root: &Path, manifest: &FileManifest) -> Result<VerifyReport, String> {
+    let mut report = VerifyReport::default();
+    let mut expected: HashMap<&str, &FileRecord> = HashMap::new();
+
+    // file lists recorded before a file became managed may still have it
+    for record in manifest.files.iter().filter(|record| !is_managed(&record.path)) {
+        expected.insert(record.path.as_str(), record);
+
+        let path = root.join(&record.path);
+
+        if !path.is_file() {
+            report.missing.push(record.path.clone());
+            continue;
+        }
+
+        let intact = fs::metadata(&path).map(|m| m.len()).ok() == Some(record.size)
+            && hash_file(&path).is_ok_and(|(_, sha256)| sha256 == record.sha256);
+
+        if !intact {
+            report.modified.push(record.path.clone());
+        }
+    }
+
+    let mut files = Vec::new();
+    collect_files(root, &mut files)
+        .map_err(|e| format!("Failed to list the installed files: {}", e))?;
+
+    for file in files {
+        if let Some(path) = relative_path(root, &file) {
+            if !expected.contains_key(path.as_str()) && !is_managed(&path) {
+                report.extra.push(path);
+            }
+        }
+    }
+
+    report.extra.sort();
+
+    Ok(report)
+}
+
+/// Writes a repaired file next to the broken one and swaps it in, so a failed
+/// write leaves the broken file behind rather than half of the good one.
+fn restore_file(entry: &mut impl Read, out_path: &Path, size: u64) -> Result<(), String> {
+    let mut temp_path = out_path.as_os_str().to_owned();
+    temp_path.push(".repair");
+    let temp_path = PathBuf::from(temp_path);
+
+    let written = File::create(&temp_path).and_then(|mut outfile| {
+        // the entry was already read once, it can't unpack to more this time
+        copy(&mut entry.take(size + 1), &mut outfile)
+    });
+
+    let result = match written {
+        Ok(written) if written == size => fs::rename(&temp_path, out_path).map_err(|e| e.to_string()),
+        Ok(_) => Err("the archive entry changed while it was read".to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+/// Compares every file in `archive` against what is installed in `root` and
+/// rewrites only the ones that are missing or differ. Returns the records of
+/// the archive contents and the paths that had to be restored.
+pub fn repair_from_archive<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    root: &Path,
+    cancel: &AtomicBool,
+) -> Result<(Vec<FileRecord>, Vec<String>), String> {
+    install::validate_archive(archive)?;
+
+    let mut records = Vec::new();
+    let mut repaired = Vec::new();
+    let mut remaining = install::MAX_UNCOMPRESSED_SIZE;
+
+    for i in 0..archive.len() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(install::CANCELLED.to_string());
+        }
+
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to read the zip entry: {}", e))?;
+
+        if entry.is_dir() {
+            continue;
+        }
+
+        let Some(enclosed_name) = entry.enclosed_name() else {
+            continue;
+        };
+
+        let Some(path) = relative_path(Path::new(""), &enclosed_name) else {
+            continue;
+        };
+
+        let out_path = root.join(&enclosed_name);
+
+        // only put back when it's gone, the changes Fluster made stay
+        let managed = is_managed(&path);
+
+        if managed && out_path.is_file() {
+            continue;
+        }
+
+        let mut hasher = Sha256::new();
+        let size = install::copy_capped(
+            &mut entry,
+            &mut hasher,
+            &mut remaining,
+            install::MAX_UNCOMPRESSED_SIZE,
+        )?;
+        let sha256 = hex::encode(hasher.finalize());
+
+        let intact = hash_file(&out_path)
+            .is_ok_and(|(disk_size, disk_sha256)| disk_size == size && disk_sha256 == sha256);
+
+        if !intact {
+            drop(entry);
+
+            let mut entry = archive
+                .by_index(i)
+                .map_err(|e| format!("Failed to read the zip entry: {}", e))?;
+
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create the parent directory: {}", e))?;
+            }
+
+            restore_file(&mut entry, &out_path, size)
+                .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+            println!("repaired: {}", path);
+            repaired.push(path.clone());
+        }
+
+        if !managed {
+            records.push(FileRecord { path, size, sha256 });
+        }
+    }
+
+    records.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok((records, repaired))
+}
@@ -0,0 +1,108 @@
+use std::net::{SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::utils::cache::AssetKey;
+
+/// Hosts broadcast every few seconds, one that hasn't been heard from in this
+/// long has most likely left the network.
+const SOURCE_EXPIRY: Duration = Duration::from_secs(30);
+const LAN_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct LanSource {
+    addr: SocketAddr,
+    last_seen: Instant,
+}
+
+static SOURCES: Mutex<Vec<LanSource>> = Mutex::new(Vec::new());
+
+/// Port this instance shares its own assets on while hosting, 0 when it isn't.
+static SERVING_PORT: AtomicU16 = AtomicU16::new(0);
+
+/// Remembers a host announcing an asset port over `lan_discovery`.
+pub fn register_source(addr: SocketAddr) {
+    let mut sources = SOURCES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    match sources.iter_mut().find(|source| source.addr == addr) {
+        Some(source) => source.last_seen = Instant::now(),
+        None => {
+            println!("found lan asset source at {}", addr);
+            sources.push(LanSource {
+                addr,
+                last_seen: Instant::now(),
+            });
+        }
+    }
+}
+
+fn sources() -> Vec<SocketAddr> {
+    let mut sources = SOURCES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    sources.retain(|source| source.last_seen.elapsed() < SOURCE_EXPIRY);
+
+    let serving_port = SERVING_PORT.load(Ordering::Relaxed);
+
+    // our own broadcasts come back to us too
+    sources
+        .iter()
+        .map(|source| source.addr)
+        .filter(|addr| serving_port == 0 || addr.port() != serving_port)
+        .collect()
+}
+
+/// Picks a free port for sharing assets with the LAN.
+pub fn reserve_port() -> std::io::Result<u16> {
+    let listener = TcpListener::bind(("0.0.0.0", 0))?;
+    Ok(listener.local_addr()?.port())
+}
+
+pub fn set_serving_port(port: Option<u16>) {
+    SERVING_PORT.store(port.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Asks the hosts on the LAN for `asset`, returning the content type they
+/// reported and the bytes from the first one that has it.
+pub async fn fetch_from_lan(asset: AssetKey) -> Option<(Option<String>, Vec<u8>)> {
+    let sources = sources();
+
+    if sources.is_empty() {
+        return None;
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(LAN_TIMEOUT)
+        .build()
+        .ok()?;
+
+    for addr in sources {
+        let url = format!("http://{}/lan/asset?{}", addr, asset.query());
+
+        let response = match client.get(&url).send().await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                println!("lan asset source {} returned {}", addr, response.status());
+                continue;
+            }
+            Err(e) => {
+                eprintln!("Failed to reach the lan asset source {}: {}", addr, e);
+                continue;
+            }
+        };
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+
+        match response.bytes().await {
+            Ok(bytes) => {
+                println!("fetched asset {:?} from {}", asset, addr);
+                return Some((content_type, bytes.to_vec()));
+            }
+            Err(e) => eprintln!("Failed to read asset data from {}: {}", addr, e),
+        }
+    }
+
+    None
+}
@@ -0,0 +1,300 @@
+use std::fs::{self, File};
+use std::io::copy;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::utils::{appdata, settings};
+
+pub const DEFAULT_MANIFEST_URL: &str = "https://cdn.simuldev.com/manifest.json";
+const MANIFEST_URL_VARIABLE: &str = "FLUSTER_MANIFEST_URL";
+const MANIFEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Key the version manifest is signed with, builds listed in a manifest that
+/// doesn't verify against it are never offered or installed. It is printed by
+/// `scripts/sign-manifest.mjs keygen`, whoever publishes the manifest at
+/// `DEFAULT_MANIFEST_URL` keeps the private half, see the README.
+///
+/// Until a signed manifest is published the builtin versions are offered
+/// instead and nothing is downloaded from `DEFAULT_MANIFEST_URL`.
+const MANIFEST_PUBLIC_KEY: Option<&str> = None;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestVersion {
+    pub id: String,
+    pub name: String,
+    pub year: u16,
+    pub url: String,
+    pub size: u64,
+    pub sha256: String,
+    #[serde(default)]
+    pub launch_args: Vec<String>,
+    /// Only set for versions from a manifest whose signature was checked, the
+    /// archives of the builtin versions have no size or checksum to match.
+    #[serde(skip)]
+    pub signed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub versions: Vec<ManifestVersion>,
+}
+
+/// What is served and cached on disk, `payload` is the manifest JSON exactly
+/// as it was signed so verifying it doesn't depend on how it gets serialized.
+/// `versions.json` is the template for the payload, `scripts/sign-manifest.mjs`
+/// wraps it into this.
+#[derive(Debug, Serialize, Deserialize)]
+struct SignedManifest {
+    payload: String,
+    signature: String,
+}
+
+impl Manifest {
+    pub fn find(&self, id: &str) -> Option<&ManifestVersion> {
+        self.versions.iter().find(|version| version.id == id)
+    }
+}
+
+pub fn manifest_url() -> String {
+    std::env::var(MANIFEST_URL_VARIABLE).unwrap_or_else(|_| settings::current().manifest_url)
+}
+
+fn cache_path() -> Result<PathBuf, String> {
+    Ok(appdata::return_appdata()?.join("manifest.json"))
+}
+
+/// The versions offered while there is no `MANIFEST_PUBLIC_KEY`, the ones
+/// Fluster shipped with before the manifest existed.
+fn builtin_manifest() -> Manifest {
+    Manifest {
+        versions: vec![ManifestVersion {
+            id: "version-997deaae24a8".to_string(),
+            name: "Roblox 2008E".to_string(),
+            year: 2008,
+            url: "https://cdn.simuldev.com/version-997deaae24a8.zip".to_string(),
+            size: 0,
+            sha256: String::new(),
+            launch_args: Vec::new(),
+            signed: false,
+        }],
+    }
+}
+
+fn verify(signed: &SignedManifest, public_key: &str) -> Result<Manifest, String> {
+    let key_bytes: [u8; 32] = hex::decode(public_key)
+        .map_err(|e| format!("Invalid manifest public key: {}", e))?
+        .try_into()
+        .map_err(|_| "Invalid manifest public key length".to_string())?;
+
+    let key = VerifyingKey::from_bytes(&key_bytes)
+        .map_err(|e| format!("Invalid manifest public key: {}", e))?;
+
+    let signature_bytes: [u8; 64] = hex::decode(signed.signature.trim())
+        .map_err(|e| format!("Invalid manifest signature: {}", e))?
+        .try_into()
+        .map_err(|_| "Invalid manifest signature length".to_string())?;
+
+    key.verify_strict(signed.payload.as_bytes(), &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| "The version manifest signature is not valid".to_string())?;
+
+    let mut manifest: Manifest = serde_json::from_str(&signed.payload)
+        .map_err(|e| format!("Failed to parse the version manifest: {}", e))?;
+
+    for version in &mut manifest.versions {
+        version.signed = true;
+    }
+
+    Ok(manifest)
+}
+
+fn read_cached_manifest(path: &Path, public_key: &str) -> Result<Manifest, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read the cached version manifest: {}", e))?;
+
+    let signed: SignedManifest = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse the cached version manifest: {}", e))?;
+
+    // the cache is checked again, AppData is not any more trustworthy than a mirror
+    verify(&signed, public_key)
+}
+
+pub fn load_cached_manifest() -> Result<Manifest, String> {
+    let public_key = MANIFEST_PUBLIC_KEY.ok_or("No version manifest signing key is configured")?;
+
+    read_cached_manifest(&cache_path()?, public_key)
+}
+
+async fn download_manifest(public_key: &str) -> Result<Manifest, String> {
+    let url = manifest_url();
+    println!("fetching version manifest from: {}", url);
+
+    let client = reqwest::Client::builder()
+        .timeout(MANIFEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create the manifest client: {}", e))?;
+
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to contact the version manifest server: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to fetch the version manifest for error code {}",
+            response.status()
+        ));
+    }
+
+    let content = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read the version manifest: {}", e))?;
+
+    let signed: SignedManifest = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse the version manifest: {}", e))?;
+
+    let manifest = verify(&signed, public_key)?;
+
+    if let Err(e) = fs::write(cache_path()?, &content) {
+        eprintln!("Failed to cache the version manifest: {}", e);
+    }
+
+    Ok(manifest)
+}
+
+/// Fetches the latest manifest, falling back to the cached copy when the
+/// server can't be reached so versions can still be listed offline.
+pub async fn fetch_manifest() -> Result<Manifest, String> {
+    let Some(public_key) = MANIFEST_PUBLIC_KEY else {
+        return Ok(builtin_manifest());
+    };
+
+    match download_manifest(public_key).await {
+        Ok(manifest) => Ok(manifest),
+        Err(e) => {
+            eprintln!("{}, using the cached version manifest", e);
+            load_cached_manifest().map_err(|cache_error| format!("{} ({})", e, cache_error))
+        }
+    }
+}
+
+/// Looks a version up in the cached manifest first and only goes to the
+/// network when it isn't there.
+pub async fn find_version(id: &str) -> Result<ManifestVersion, String> {
+    if let Ok(manifest) = load_cached_manifest() {
+        if let Some(version) = manifest.find(id) {
+            return Ok(version.clone());
+        }
+    }
+
+    fetch_manifest()
+        .await?
+        .find(id)
+        .cloned()
+        .ok_or_else(|| format!("{} is not listed in the version manifest", id))
+}
+
+pub fn cached_launch_args(id: &str) -> Vec<String> {
+    load_cached_manifest()
+        .ok()
+        .and_then(|manifest| manifest.find(id).map(|version| version.launch_args.clone()))
+        .unwrap_or_default()
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let mut hasher = Sha256::new();
+
+    copy(&mut file, &mut hasher).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use super::*;
+
+    const PAYLOAD: &str = r#"{"versions":[{"id":"version-997deaae24a8","name":"Roblox 2008E","year":2008,"url":"https://cdn.simuldev.com/version-997deaae24a8.zip","size":1024,"sha256":"00"}]}"#;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    fn public_key() -> String {
+        hex::encode(signing_key().verifying_key().to_bytes())
+    }
+
+    fn sign(payload: &str) -> SignedManifest {
+        SignedManifest {
+            payload: payload.to_string(),
+            signature: hex::encode(signing_key().sign(payload.as_bytes()).to_bytes()),
+        }
+    }
+
+    #[test]
+    fn accepts_a_valid_signature() {
+        let manifest = verify(&sign(PAYLOAD), &public_key()).unwrap();
+        let version = manifest.find("version-997deaae24a8").unwrap();
+
+        assert_eq!(version.size, 1024);
+        assert!(version.signed);
+    }
+
+    #[test]
+    fn rejects_a_tampered_payload() {
+        let mut signed = sign(PAYLOAD);
+        signed.payload = signed.payload.replace("1024", "2048");
+
+        assert!(verify(&signed, &public_key()).is_err());
+    }
+
+    #[test]
+    fn rejects_a_tampered_signature() {
+        let mut signed = sign(PAYLOAD);
+        let flipped = if signed.signature.starts_with('0') { "1" } else { "0" };
+        signed.signature.replace_range(0..1, flipped);
+
+        assert!(verify(&signed, &public_key()).is_err());
+    }
+
+    #[test]
+    fn rejects_a_short_signature() {
+        let mut signed = sign(PAYLOAD);
+        signed.signature.truncate(64);
+
+        assert!(verify(&signed, &public_key()).is_err());
+    }
+
+    #[test]
+    fn rejects_a_signature_from_another_key() {
+        let other = SigningKey::from_bytes(&[8; 32]);
+        let signed = SignedManifest {
+            payload: PAYLOAD.to_string(),
+            signature: hex::encode(other.sign(PAYLOAD.as_bytes()).to_bytes()),
+        };
+
+        assert!(verify(&signed, &public_key()).is_err());
+    }
+
+    #[test]
+    fn refuses_a_cached_manifest_that_does_not_verify() {
+        let path = std::env::temp_dir().join(format!("fluster-manifest-{}.json", std::process::id()));
+
+        let mut signed = sign(PAYLOAD);
+        fs::write(&path, serde_json::to_string(&signed).unwrap()).unwrap();
+        assert!(read_cached_manifest(&path, &public_key()).is_ok());
+
+        signed.payload = signed.payload.replace("Roblox 2008E", "Roblox 2009");
+        fs::write(&path, serde_json::to_string(&signed).unwrap()).unwrap();
+        assert!(read_cached_manifest(&path, &public_key()).is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{appdata, settings};
+
+/// A mirror that failed this many times in a row is only tried after the
+/// healthy ones, until it works again.
+const UNHEALTHY_AFTER: u32 = 3;
+
+/// Held across every read-modify-write of `mirrors.json`, downloads running
+/// side by side would otherwise drop each other's updates.
+static HEALTH_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MirrorHealth {
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub last_success: Option<u64>,
+    pub last_failure: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MirrorConfig {
+    pub mirrors: Vec<String>,
+    pub health: HashMap<String, MirrorHealth>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HealthFile {
+    #[serde(default)]
+    health: HashMap<String, MirrorHealth>,
+}
+
+fn health_path() -> Result<PathBuf, String> {
+    Ok(appdata::return_appdata()?.join("mirrors.json"))
+}
+
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn read_health(path: &Path) -> HealthFile {
+    let Ok(content) = fs::read_to_string(path) else {
+        return HealthFile::default();
+    };
+
+    match serde_json::from_str(&content) {
+        Ok(file) => file,
+        Err(e) => {
+            // moved aside rather than silently started over on top of, the
+            // file is read again on every download
+            let backup = path.with_extension(format!("json.{}.bak", current_timestamp()));
+
+            match fs::rename(path, &backup) {
+                Ok(()) => eprintln!("The mirror health is corrupted ({}), kept a copy at {:?}", e, backup),
+                Err(copy_error) => eprintln!(
+                    "The mirror health is corrupted ({}), and failed to keep a copy: {}",
+                    e, copy_error
+                ),
+            }
+
+            HealthFile::default()
+        }
+    }
+}
+
+fn load_health() -> HealthFile {
+    match health_path() {
+        Ok(path) => read_health(&path),
+        Err(_) => HealthFile::default(),
+    }
+}
+
+fn save_health(file: &HealthFile) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(file)
+        .map_err(|e| format!("Failed to serialize the mirror health: {}", e))?;
+
+    // swapped in whole, a crash mid-write leaves the previous health behind
+    let path = health_path()?;
+    let temp_path = path.with_extension("json.tmp");
+
+    File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, &path))
+        .map_err(|e| format!("Failed to save the mirror health: {}", e))
+}
+
+pub fn load_mirrors() -> MirrorConfig {
+    let _guard = HEALTH_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    MirrorConfig {
+        mirrors: settings::current().mirrors,
+        health: load_health().health,
+    }
+}
+
+pub fn validate_mirror(mirror: &str) -> Result<(), String> {
+    let url = reqwest::Url::parse(mirror).map_err(|e| format!("{} is not a valid URL: {}", mirror, e))?;
+
+    match url.scheme() {
+        "http" | "https" => Ok(()),
+        "file" => url
+            .to_file_path()
+            .map(|_| ())
+            .map_err(|_| format!("{} is not a valid local path", mirror)),
+        scheme => Err(format!("{} mirrors are not supported", scheme)),
+    }
+}
+
+/// Forgets the health of mirrors that are no longer configured.
+pub fn prune_health(mirrors: &[String]) {
+    let _guard = HEALTH_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut file = load_health();
+    let count = file.health.len();
+
+    file.health.retain(|mirror, _| mirrors.contains(mirror));
+
+    if file.health.len() != count {
+        save_health(&file).unwrap_or_else(|e| eprintln!("{}", e));
+    }
+}
+
+/// Returns `(mirror, url)` pairs to try for `version`, in the configured order
+/// with unhealthy mirrors moved to the back and `fallback_url` from the version
+/// manifest last.
+pub fn candidate_urls(version: &str, fallback_url: &str) -> Vec<(String, String)> {
+    let config = load_mirrors();
+
+    let mut mirrors: Vec<&String> = config.mirrors.iter().collect();
+    mirrors.sort_by_key(|mirror| {
+        config
+            .health
+            .get(*mirror)
+            .is_some_and(|health| health.consecutive_failures >= UNHEALTHY_AFTER)
+    });
+
+    let mut candidates: Vec<(String, String)> = mirrors
+        .into_iter()
+        .map(|mirror| (mirror.clone(), format!("{}/{}.zip", mirror, version)))
+        .collect();
+
+    candidates.push((fallback_url.to_string(), fallback_url.to_string()));
+    candidates
+}
+
+fn update_health(mirror: &str, update: impl FnOnce(&mut MirrorHealth)) {
+    // the manifest fallback isn't a configured mirror, so it isn't tracked
+    if !settings::current().mirrors.iter().any(|m| m == mirror) {
+        return;
+    }
+
+    let _guard = HEALTH_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut file = load_health();
+
+    update(file.health.entry(mirror.to_string()).or_default());
+
+    if let Err(e) = save_health(&file) {
+        eprintln!("{}", e);
+    }
+}
+
+pub fn record_success(mirror: &str) {
+    update_health(mirror, |health| {
+        health.successes += 1;
+        health.consecutive_failures = 0;
+        health.last_success = Some(current_timestamp());
+    });
+}
+
+pub fn record_failure(mirror: &str, error: &str) {
+    update_health(mirror, |health| {
+        health.failures += 1;
+        health.consecutive_failures += 1;
+        health.last_failure = Some(current_timestamp());
+        health.last_error = Some(error.to_string());
+    });
+}
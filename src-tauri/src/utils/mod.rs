@@ -1,3 +1,19 @@
 pub mod client;
 pub mod network;
-pub mod appdata;
\ No newline at end of file
+pub mod appdata;
+pub mod download;
+pub mod install;
+pub mod manifest;
+pub mod integrity;
+pub mod mirrors;
+pub mod cache;
+pub mod overrides;
+pub mod pack;
+pub mod places;
+pub mod lan_assets;
+pub mod server;
+pub mod hosts;
+pub mod supervisor;
+pub mod sessions;
+pub mod stats;
+pub mod settings;
\ No newline at end of file
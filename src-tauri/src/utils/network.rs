@@ -1,11 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::net::{Ipv4Addr, SocketAddr, TcpListener, UdpSocket};
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
-const MULTICAST_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 42, 17);
-const MULTICAST_PORT: u16 = 58432;
+use crate::utils::settings;
+
 const BROADCAST_INTERVAL: Duration = Duration::from_secs(5);
 
 pub mod lan_discovery {
@@ -15,6 +15,8 @@ pub mod lan_discovery {
     pub struct ServerInfo {
         pub port: u16,
         pub version: String,
+        #[serde(default)]
+        pub asset_port: Option<u16>,
     }
 
     pub struct Client {
@@ -47,7 +49,7 @@ pub mod lan_discovery {
         }
     }
 
-    pub fn start_server(port: u16, version: &str) -> std::io::Result<UdpServer> {
+    pub fn start_server(port: u16, version: &str, asset_port: Option<u16>) -> std::io::Result<UdpServer> {
         let tcp_listener = TcpListener::bind(("0.0.0.0", port))?;
         let actual_port = tcp_listener.local_addr()?.port();
 
@@ -57,17 +59,21 @@ pub mod lan_discovery {
         let (shutdown_tx, shutdown_rx) = mpsc::channel();
         let version = version.to_string();
 
+        let settings = settings::current();
+        let destination = (settings.multicast_address, settings.multicast_port);
+
         let broadcast_thread = thread::spawn(move || {
-            let destination = (MULTICAST_ADDR, MULTICAST_PORT);
 
             loop {
-                if shutdown_rx.try_recv().is_ok() {
+                // a dropped handle stops the broadcast as well
+                if !matches!(shutdown_rx.try_recv(), Err(TryRecvError::Empty)) {
                     break;
                 }
 
                 let server_info = ServerInfo {
                     port: actual_port,
                     version: version.clone(),
+                    asset_port,
                 };
 
                 let message = serde_json::to_string(&server_info).unwrap_or_default();
@@ -88,9 +94,11 @@ pub mod lan_discovery {
     }
 
     pub fn start_discovery() -> std::io::Result<Client> {
-        let udp_socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, MULTICAST_PORT))?;
+        let settings = settings::current();
+
+        let udp_socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, settings.multicast_port))?;
 
-        udp_socket.join_multicast_v4(&MULTICAST_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+        udp_socket.join_multicast_v4(&settings.multicast_address, &Ipv4Addr::UNSPECIFIED)?;
 
         let (addr_tx, addr_rx) = mpsc::channel();
         let (shutdown_tx, shutdown_rx) = mpsc::channel();
@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::utils::appdata;
+
+/// Version of the client that was launched last, its own overrides take
+/// precedence over the shared ones.
+static ACTIVE_VERSION: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Debug, Serialize)]
+pub struct AssetOverride {
+    pub asset_id: u64,
+    pub file_name: String,
+    pub version: Option<String>,
+    pub size: u64,
+}
+
+pub fn set_active_version(version: &str) {
+    *ACTIVE_VERSION.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) =
+        Some(version.to_string());
+}
+
+fn active_version() -> Option<String> {
+    ACTIVE_VERSION
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// `overrides/` holds the overrides for every version, `overrides/<version>/`
+/// the ones for a single version.
+pub fn overrides_dir(version: Option<&str>) -> Result<PathBuf, String> {
+    let mut dir = appdata::return_appdata()?.join("overrides");
+
+    if let Some(version) = version {
+        appdata::validate_version(version)?;
+        dir = dir.join(version);
+    }
+
+    if !dir.exists() {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create the overrides directory: {}", e))?;
+    }
+
+    Ok(dir)
+}
+
+fn override_asset_id(path: &Path) -> Option<u64> {
+    if !path.is_file() {
+        return None;
+    }
+
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+fn find_in(dir: &Path, asset_id: u64) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| override_asset_id(path) == Some(asset_id))
+}
+
+/// Looks for an `<asset_id>.*` file for the active version first and then in
+/// the shared overrides.
+pub fn find_override(asset_id: u64) -> Option<PathBuf> {
+    if let Some(version) = active_version() {
+        if let Some(path) = overrides_dir(Some(&version))
+            .ok()
+            .and_then(|dir| find_in(&dir, asset_id))
+        {
+            return Some(path);
+        }
+    }
+
+    find_in(&overrides_dir(None).ok()?, asset_id)
+}
+
+fn list_dir(dir: &Path, version: Option<&str>, overrides: &mut Vec<AssetOverride>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if let Some(asset_id) = override_asset_id(&path) {
+            overrides.push(AssetOverride {
+                asset_id,
+                file_name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                version: version.map(|version| version.to_string()),
+                size: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+            });
+        }
+    }
+}
+
+/// Lists the overrides for `version`, or every override when no version is given.
+pub fn list_overrides(version: Option<&str>) -> Result<Vec<AssetOverride>, String> {
+    let mut overrides = Vec::new();
+
+    match version {
+        Some(version) => list_dir(&overrides_dir(Some(version))?, Some(version), &mut overrides),
+        None => {
+            let dir = overrides_dir(None)?;
+            list_dir(&dir, None, &mut overrides);
+
+            let entries = fs::read_dir(&dir)
+                .map_err(|e| format!("Failed to read the overrides directory: {}", e))?;
+
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if path.is_dir() {
+                    let version = path.file_name().map(|name| name.to_string_lossy().to_string());
+                    list_dir(&path, version.as_deref(), &mut overrides);
+                }
+            }
+        }
+    }
+
+    overrides.sort_by(|a, b| (&a.version, a.asset_id).cmp(&(&b.version, b.asset_id)));
+
+    Ok(overrides)
+}
+
+fn remove_in(dir: &Path, asset_id: u64) -> Result<bool, String> {
+    let mut removed = false;
+
+    while let Some(path) = find_in(dir, asset_id) {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove {:?}: {}", path, e))?;
+        removed = true;
+    }
+
+    Ok(removed)
+}
+
+/// Copies `source` in as the override for `asset_id`, replacing any previous one.
+pub fn add_override(asset_id: u64, source: &Path, version: Option<&str>) -> Result<(), String> {
+    if !source.is_file() {
+        return Err(format!("{:?} is not a file", source));
+    }
+
+    let dir = overrides_dir(version)?;
+    remove_in(&dir, asset_id)?;
+
+    let file_name = match source.extension() {
+        Some(extension) => format!("{}.{}", asset_id, extension.to_string_lossy()),
+        None => asset_id.to_string(),
+    };
+
+    fs::copy(source, dir.join(file_name))
+        .map_err(|e| format!("Failed to copy the override: {}", e))?;
+
+    Ok(())
+}
+
+pub fn remove_override(asset_id: u64, version: Option<&str>) -> Result<bool, String> {
+    remove_in(&overrides_dir(version)?, asset_id)
+}
@@ -0,0 +1,256 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::utils::cache::{self, AssetKey};
+use crate::utils::{install, places};
+
+pub const PACK_EXTENSION: &str = "flusterpack";
+const PACK_FORMAT: u32 = 1;
+const PACK_MANIFEST: &str = "pack.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PackAsset {
+    asset_id: Option<u64>,
+    version: Option<u64>,
+    asset_version_id: Option<u64>,
+    content_type: String,
+    file: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PackManifest {
+    format: u32,
+    created_at: u64,
+    assets: Vec<PackAsset>,
+}
+
+impl PackAsset {
+    fn asset_key(&self) -> Option<AssetKey> {
+        match (self.asset_id, self.asset_version_id) {
+            (_, Some(asset_version_id)) => Some(AssetKey::AssetVersion(asset_version_id)),
+            (Some(id), None) => Some(AssetKey::Asset {
+                id,
+                version: self.version,
+            }),
+            (None, None) => None,
+        }
+    }
+}
+
+pub fn pack_path(path: &Path) -> PathBuf {
+    if path.extension().is_some_and(|extension| extension == PACK_EXTENSION) {
+        path.to_path_buf()
+    } else {
+        let mut path = path.as_os_str().to_os_string();
+        path.push(".");
+        path.push(PACK_EXTENSION);
+        PathBuf::from(path)
+    }
+}
+
+/// Which cached assets go into a pack, `None` for both means all of them.
+struct Selection {
+    asset_ids: Option<Vec<u64>>,
+    place_assets: Option<HashSet<AssetKey>>,
+}
+
+impl Selection {
+    fn includes(&self, asset: AssetKey, asset_id: Option<u64>) -> bool {
+        if self.asset_ids.is_none() && self.place_assets.is_none() {
+            return true;
+        }
+
+        // assetversionid entries don't say which asset they belong to, so
+        // they only match asset ids through a place referencing them
+        let listed = self
+            .asset_ids
+            .as_ref()
+            .is_some_and(|asset_ids| asset_id.is_some_and(|id| asset_ids.contains(&id)));
+
+        let referenced = self
+            .place_assets
+            .as_ref()
+            .is_some_and(|place_assets| place_assets.contains(&asset));
+
+        listed || referenced
+    }
+}
+
+fn write_pack(destination: &Path, selection: &Selection) -> Result<usize, String> {
+    let file =
+        File::create(destination).map_err(|e| format!("Failed to create the asset pack: {}", e))?;
+
+    let mut writer = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut assets = Vec::new();
+
+    for entry in cache::entries() {
+        let Some(asset) = entry.asset_key() else {
+            continue;
+        };
+
+        if !selection.includes(asset, entry.asset_id) {
+            continue;
+        }
+
+        let Some(bytes) = cache::read(asset) else {
+            continue;
+        };
+
+        let file = format!("assets/{}", asset.cache_key());
+
+        writer
+            .start_file(file.as_str(), options)
+            .map_err(|e| format!("Failed to add {} to the asset pack: {}", file, e))?;
+        writer
+            .write_all(&bytes)
+            .map_err(|e| format!("Failed to add {} to the asset pack: {}", file, e))?;
+
+        assets.push(PackAsset {
+            asset_id: entry.asset_id,
+            version: entry.version,
+            asset_version_id: entry.asset_version_id,
+            content_type: entry.content_type,
+            file,
+        });
+    }
+
+    let count = assets.len();
+
+    let manifest = serde_json::to_vec_pretty(&PackManifest {
+        format: PACK_FORMAT,
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        assets,
+    })
+    .map_err(|e| format!("Failed to serialize the asset pack manifest: {}", e))?;
+
+    writer
+        .start_file(PACK_MANIFEST, options)
+        .map_err(|e| format!("Failed to write the asset pack manifest: {}", e))?;
+    writer
+        .write_all(&manifest)
+        .map_err(|e| format!("Failed to write the asset pack manifest: {}", e))?;
+
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to finish the asset pack: {}", e))?;
+
+    Ok(count)
+}
+
+/// Writes the cached assets into a `.flusterpack` at `destination`, either
+/// every asset or only the ones in `asset_ids` and the ones the place file at
+/// `place` references. Returns how many were packed.
+pub fn export_pack(
+    destination: &Path,
+    asset_ids: Option<&[u64]>,
+    place: Option<&Path>,
+) -> Result<usize, String> {
+    let place_assets = match place {
+        Some(place) => Some(places::scan_place_file(place)?.into_iter().collect()),
+        None => None,
+    };
+
+    let selection = Selection {
+        asset_ids: asset_ids.map(<[u64]>::to_vec),
+        place_assets,
+    };
+
+    let result = write_pack(destination, &selection);
+
+    if result.is_err() {
+        let _ = fs::remove_file(destination);
+    }
+
+    result
+}
+
+/// Adds every asset in the pack at `source` to the cache and returns how many
+/// were imported, leaving out the ones too big for the cache or evicted again
+/// to make room for the rest.
+pub fn import_pack(source: &Path) -> Result<usize, String> {
+    let file = File::open(source).map_err(|e| format!("Failed to open the asset pack: {}", e))?;
+
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| format!("Failed to read the asset pack: {}", e))?;
+
+    // packs get passed around between machines, so they get the same checks
+    // as client archives
+    install::validate_archive(&mut archive)?;
+
+    let manifest: PackManifest = {
+        let entry = archive
+            .by_name(PACK_MANIFEST)
+            .map_err(|_| "The file is not an asset pack".to_string())?;
+
+        serde_json::from_reader(entry)
+            .map_err(|e| format!("Failed to parse the asset pack manifest: {}", e))?
+    };
+
+    if manifest.format > PACK_FORMAT {
+        return Err(
+            "The asset pack was made by a newer version of Fluster, update to import it".to_string(),
+        );
+    }
+
+    let mut stored = Vec::new();
+
+    for asset in &manifest.assets {
+        let Some(key) = asset.asset_key() else {
+            continue;
+        };
+
+        let mut entry = match archive.by_name(&asset.file) {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("Skipping {} from the asset pack: {}", asset.file, e);
+                continue;
+            }
+        };
+
+        // the header was checked already, but only the compressed stream is
+        // bounded by it, so make sure the entry doesn't unpack to more
+        let size = entry.size();
+        let mut bytes = Vec::new();
+
+        (&mut entry)
+            .take(size + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read {} from the asset pack: {}", asset.file, e))?;
+
+        if bytes.len() as u64 > size {
+            cache::flush();
+            return Err(format!(
+                "{} in the asset pack unpacks to more than the {} bytes it claims",
+                asset.file, size
+            ));
+        }
+
+        if cache::insert_deferred(key, &asset.content_type, &bytes)? {
+            stored.push(key);
+        }
+    }
+
+    cache::flush();
+
+    let imported = stored.iter().filter(|key| cache::contains(**key)).count();
+
+    println!(
+        "imported {} of {} assets from {:?}",
+        imported,
+        manifest.assets.len(),
+        source
+    );
+
+    Ok(imported)
+}
@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::utils::cache::AssetKey;
+
+fn leading_number(text: &str) -> Option<u64> {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+
+    text[..end].parse().ok()
+}
+
+/// Reads the `name=<number>` query parameter following an asset URL.
+fn query_number(query: &str, name: &str) -> Option<u64> {
+    let end = query
+        .find(|c: char| c.is_whitespace() || c == '<' || c == '"' || c == '\'')
+        .unwrap_or(query.len());
+
+    query[..end]
+        .split(['&', '?'])
+        .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+        .and_then(leading_number)
+}
+
+/// Finds the asset references in the bytes of a place or model file, see
+/// [`scan_place_file`].
+fn scan_bytes(bytes: &[u8]) -> Vec<AssetKey> {
+    let text = String::from_utf8_lossy(bytes)
+        .to_ascii_lowercase()
+        .replace("&amp;", "&");
+
+    let mut found = Vec::new();
+
+    for (index, pattern) in text.match_indices("rbxassetid://") {
+        if let Some(id) = leading_number(&text[index + pattern.len()..]) {
+            found.push((index, AssetKey::Asset { id, version: None }));
+        }
+    }
+
+    for pattern in ["asset/?", "asset?"] {
+        for (index, _) in text.match_indices(pattern) {
+            let query = &text[index + pattern.len() - 1..];
+
+            if let Some(asset_version_id) = query_number(query, "assetversionid") {
+                found.push((index, AssetKey::AssetVersion(asset_version_id)));
+            } else if let Some(id) = query_number(query, "id") {
+                found.push((
+                    index,
+                    AssetKey::Asset {
+                        id,
+                        version: query_number(query, "version"),
+                    },
+                ));
+            }
+        }
+    }
+
+    // the forms are searched one after another, put them back in file order
+    found.sort_by_key(|(index, _)| *index);
+
+    let mut seen = HashSet::new();
+
+    found
+        .into_iter()
+        .map(|(_, asset)| asset)
+        .filter(|asset| seen.insert(*asset))
+        .collect()
+}
+
+/// Collects every asset a place or model file points at, from both
+/// `rbxassetid://<id>` content strings and `asset/?id=<id>` URLs, in the order
+/// they first appear.
+///
+/// Binary files are searched the same way, which only finds the references in
+/// chunks that aren't compressed.
+pub fn scan_place_file(path: &Path) -> Result<Vec<AssetKey>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read the place file: {}", e))?;
+
+    let assets = scan_bytes(&bytes);
+
+    println!("found {} asset references in {:?}", assets.len(), path);
+
+    Ok(assets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(id: u64) -> AssetKey {
+        AssetKey::Asset { id, version: None }
+    }
+
+    #[test]
+    fn keeps_the_order_references_appear_in() {
+        let place = br#"<Content name="MeshId"><url>http://www.roblox.com/asset/?id=20</url></Content>
+<Content name="TextureId"><url>rbxassetid://10</url></Content>
+<Content name="SoundId"><url>http://www.roblox.com/asset?id=30</url></Content>
+<string name="Source">game:GetService("InsertService"):LoadAsset("rbxassetid://5")</string>"#;
+
+        assert_eq!(scan_bytes(place), vec![asset(20), asset(10), asset(30), asset(5)]);
+    }
+
+    #[test]
+    fn reads_versions_and_asset_version_ids() {
+        let place = br#"<url>http://www.roblox.com/asset/?id=20&amp;version=3</url>
+<url>http://www.roblox.com/asset/?assetversionid=777</url>
+<url>http://www.roblox.com/asset/?id=20&amp;assetversionid=778</url>"#;
+
+        assert_eq!(
+            scan_bytes(place),
+            vec![
+                AssetKey::Asset { id: 20, version: Some(3) },
+                AssetKey::AssetVersion(777),
+                AssetKey::AssetVersion(778),
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_each_reference_once() {
+        let place = b"rbxassetid://10 rbxassetid://10 http://www.roblox.com/asset/?id=10 \
+http://www.roblox.com/asset/?id=10&version=2 http://www.roblox.com/asset/?ID=10&version=2";
+
+        assert_eq!(
+            scan_bytes(place),
+            vec![asset(10), AssetKey::Asset { id: 10, version: Some(2) }]
+        );
+    }
+
+    #[test]
+    fn finds_references_between_binary_data() {
+        let mut place = b"<roblox!\x89\xff\x0d\x0a\x1a\x0a\0\0\0\x02".to_vec();
+        place.extend_from_slice(b"\x0d\0\0\0rbxassetid://42\xff\xfe\0");
+        place.extend_from_slice(b"\x1a\0\0\0http://www.roblox.com/asset/?id=43\0\x80");
+
+        assert_eq!(scan_bytes(&place), vec![asset(42), asset(43)]);
+    }
+
+    #[test]
+    fn ignores_references_without_a_number() {
+        assert!(scan_bytes(b"rbxassetid:// http://www.roblox.com/asset/?id=abc").is_empty());
+    }
+}
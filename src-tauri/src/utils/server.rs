@@ -0,0 +1,261 @@
+use std::net::{IpAddr, Ipv4Addr, TcpListener};
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::Mutex;
+
+use rocket::figment::providers::{Env, Format, Serialized, Toml};
+use rocket::figment::{Figment, Profile};
+use rocket::Shutdown;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::settings;
+
+const LOCAL_DOMAIN: &str = "www.fluster.is";
+
+fn default_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    80
+}
+
+fn default_fallback_port() -> u16 {
+    8080
+}
+
+fn default_workers() -> usize {
+    1
+}
+
+/// How clients find the local web server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DomainMode {
+    /// `www.fluster.is` is pointed at it in the system hosts file.
+    #[default]
+    Hosts,
+    /// Clients are handed its loopback address directly, through the launch
+    /// URLs and their `AppSettings.xml`, so nothing system wide changes.
+    Loopback,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerConfig {
+    #[serde(default = "default_address")]
+    pub address: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Used when `port` is taken or needs privileges we don't have, which is
+    /// always the case for port 80 on Linux without root.
+    #[serde(default = "default_fallback_port")]
+    pub fallback_port: u16,
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+    #[serde(default)]
+    pub domain_mode: DomainMode,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            address: default_address(),
+            port: default_port(),
+            fallback_port: default_fallback_port(),
+            workers: default_workers(),
+            domain_mode: DomainMode::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStatus {
+    pub state: &'static str,
+    pub address: String,
+    pub port: u16,
+    pub error: Option<String>,
+}
+
+static STATUS: Mutex<Option<ServerStatus>> = Mutex::new(None);
+
+/// Port the web server actually listens on, 0 until it is up.
+static ACTIVE_PORT: AtomicU16 = AtomicU16::new(0);
+
+static SHUTDOWN: Mutex<Option<Shutdown>> = Mutex::new(None);
+static RESTART: AtomicBool = AtomicBool::new(false);
+static STOPPED: AtomicBool = AtomicBool::new(false);
+
+pub fn load_server_config() -> ServerConfig {
+    settings::current().server
+}
+
+pub fn validate_server_config(config: &ServerConfig) -> Result<(), String> {
+    config
+        .address
+        .parse::<IpAddr>()
+        .map_err(|_| format!("{} is not a valid IP address", config.address))?;
+
+    if config.port == 0 || config.fallback_port == 0 {
+        return Err("The web server ports must be greater than zero".to_string());
+    }
+
+    if config.workers == 0 {
+        return Err("The web server needs at least one worker".to_string());
+    }
+
+    Ok(())
+}
+
+fn can_bind(address: IpAddr, port: u16) -> bool {
+    TcpListener::bind((address, port)).is_ok()
+}
+
+/// Builds the Rocket configuration from the saved settings, which win over
+/// `Rocket.toml`. Only `ROCKET_*` variables still override them, and the
+/// fallback port is used when the configured one can't be bound.
+pub fn rocket_figment(config: &ServerConfig) -> Result<Figment, String> {
+    let figment = |port: u16| {
+        Figment::from(rocket::Config::default())
+            .merge(Toml::file(Env::var_or("ROCKET_CONFIG", "Rocket.toml")).nested())
+            // global so a profile section in Rocket.toml can't take them back
+            .merge(Serialized::global("address", config.address.as_str()))
+            .merge(Serialized::global("port", port))
+            .merge(Serialized::global("workers", config.workers))
+            .merge(Serialized::global("shutdown.timeout", 0))
+            .merge(Env::prefixed("ROCKET_").ignore(&["PROFILE"]).global())
+            .select(Profile::from_env_or("ROCKET_PROFILE", rocket::Config::DEFAULT_PROFILE))
+    };
+
+    let resolve = |figment: &Figment| {
+        rocket::Config::try_from(figment)
+            .map_err(|e| format!("Invalid web server settings: {}", e))
+    };
+
+    let primary = figment(config.port);
+    let resolved = resolve(&primary)?;
+
+    if can_bind(resolved.address, resolved.port) {
+        return Ok(primary);
+    }
+
+    if Env::var("ROCKET_PORT").is_some() {
+        return Err(format!(
+            "Port {} set through ROCKET_PORT is not available on {}",
+            resolved.port, resolved.address
+        ));
+    }
+
+    println!(
+        "port {} is not available, trying port {}",
+        resolved.port, config.fallback_port
+    );
+
+    let fallback = figment(config.fallback_port);
+    let resolved_fallback = resolve(&fallback)?;
+
+    if can_bind(resolved_fallback.address, resolved_fallback.port) {
+        return Ok(fallback);
+    }
+
+    Err(format!(
+        "Neither port {} nor port {} is available on {}, make sure no other web server is using them or pick other ports in the settings",
+        resolved.port, resolved_fallback.port, resolved.address
+    ))
+}
+
+pub fn set_status(state: &'static str, address: &str, port: u16, error: Option<String>) -> ServerStatus {
+    let status = ServerStatus {
+        state,
+        address: address.to_string(),
+        port,
+        error,
+    };
+
+    if state == "running" {
+        ACTIVE_PORT.store(port, Ordering::Relaxed);
+    }
+
+    *STATUS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(status.clone());
+
+    status
+}
+
+pub fn status() -> Option<ServerStatus> {
+    STATUS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Hands over the handle that stops the web server once it is ignited.
+/// Returns false when the app is already on its way out.
+pub fn set_shutdown(shutdown: Option<Shutdown>) -> bool {
+    *SHUTDOWN.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = shutdown;
+    !STOPPED.load(Ordering::Relaxed)
+}
+
+fn notify_shutdown() -> bool {
+    match SHUTDOWN.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take() {
+        Some(shutdown) => {
+            shutdown.notify();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Stops the web server for good.
+pub fn stop() {
+    STOPPED.store(true, Ordering::Relaxed);
+    RESTART.store(false, Ordering::Relaxed);
+    notify_shutdown();
+}
+
+/// Stops the web server so it comes back up with the current settings.
+/// Returns false when it isn't running, in which case it has to be started.
+pub fn restart() -> bool {
+    RESTART.store(true, Ordering::Relaxed);
+
+    let notified = notify_shutdown();
+
+    if !notified {
+        RESTART.store(false, Ordering::Relaxed);
+    }
+
+    notified
+}
+
+/// Whether the web server that just stopped should start again.
+pub fn take_restart() -> bool {
+    RESTART.swap(false, Ordering::Relaxed) && !STOPPED.load(Ordering::Relaxed)
+}
+
+fn loopback_host(address: &str) -> String {
+    match address.parse::<IpAddr>() {
+        Ok(address) if address.is_unspecified() => Ipv4Addr::LOCALHOST.to_string(),
+        Ok(IpAddr::V6(address)) => format!("[{}]", address),
+        Ok(address) => address.to_string(),
+        Err(_) => Ipv4Addr::LOCALHOST.to_string(),
+    }
+}
+
+/// Where clients reach the local web server, with the port spelled out when
+/// it isn't the default one.
+pub fn base_url() -> String {
+    let config = load_server_config();
+
+    let port = match ACTIVE_PORT.load(Ordering::Relaxed) {
+        0 => config.port,
+        port => port,
+    };
+
+    let host = match config.domain_mode {
+        DomainMode::Hosts => LOCAL_DOMAIN.to_string(),
+        DomainMode::Loopback => loopback_host(&config.address),
+    };
+
+    if port == 80 {
+        format!("http://{}", host)
+    } else {
+        format!("http://{}:{}", host, port)
+    }
+}
@@ -0,0 +1,150 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::appdata;
+use crate::utils::supervisor::{Launch, LaunchExit, LaunchKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionMode {
+    Solo,
+    Host,
+    Join,
+}
+
+impl From<LaunchKind> for SessionMode {
+    fn from(kind: LaunchKind) -> Self {
+        match kind {
+            LaunchKind::Client => SessionMode::Solo,
+            LaunchKind::Server => SessionMode::Host,
+            LaunchKind::Join => SessionMode::Join,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub version: String,
+    pub mode: SessionMode,
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub place_file: Option<String>,
+    pub server_address: Option<String>,
+    /// `None` when the client was killed or still running when Fluster quit.
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SessionTotals {
+    pub sessions: usize,
+    pub total_play_time: u64,
+    pub longest_session: u64,
+    pub last_played: u64,
+    pub solo: usize,
+    pub host: usize,
+    pub join: usize,
+    /// Sessions that ended with a non-zero exit code.
+    pub failed: usize,
+}
+
+impl Session {
+    pub fn from_launch(launch: &Launch, ended_at: u64, exit_code: Option<i32>) -> Self {
+        Session {
+            version: launch.version.clone(),
+            mode: launch.kind.into(),
+            started_at: launch.started_at,
+            ended_at,
+            place_file: launch.details.place_file.clone(),
+            server_address: launch.details.server_address.clone(),
+            exit_code,
+        }
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.ended_at.saturating_sub(self.started_at)
+    }
+}
+
+impl From<&LaunchExit> for Session {
+    fn from(exit: &LaunchExit) -> Self {
+        Session::from_launch(&exit.launch, exit.ended_at, exit.exit_code)
+    }
+}
+
+/// One session per line next to `version_stats.json`, so recording one never
+/// rewrites the ones before it.
+fn sessions_path() -> Result<PathBuf, String> {
+    Ok(appdata::return_versions()?.join("sessions.jsonl"))
+}
+
+pub fn record_session(session: &Session) -> Result<(), String> {
+    let line = serde_json::to_string(session)
+        .map_err(|e| format!("Failed to serialize the session: {}", e))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(sessions_path()?)
+        .map_err(|e| format!("Failed to open the session history: {}", e))?;
+
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to record the session: {}", e))
+}
+
+fn load_sessions() -> Vec<Session> {
+    let Ok(content) = sessions_path().and_then(|path| {
+        fs::read_to_string(path).map_err(|e| e.to_string())
+    }) else {
+        return Vec::new();
+    };
+
+    // a line cut short by a crash shouldn't take the rest of the history with it
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Sessions newest first, of one version or all of them.
+pub fn history(version: Option<&str>, limit: Option<usize>) -> Vec<Session> {
+    let mut sessions: Vec<Session> = load_sessions()
+        .into_iter()
+        .filter(|session| version.is_none_or(|version| session.version == version))
+        .collect();
+
+    sessions.sort_by_key(|session| Reverse(session.started_at));
+    sessions.truncate(limit.unwrap_or(usize::MAX));
+
+    sessions
+}
+
+/// Totals for every version that has been played, keyed by version.
+pub fn totals() -> BTreeMap<String, SessionTotals> {
+    let mut totals: BTreeMap<String, SessionTotals> = BTreeMap::new();
+
+    for session in load_sessions() {
+        let duration = session.duration();
+        let entry = totals.entry(session.version.clone()).or_default();
+
+        entry.sessions += 1;
+        entry.total_play_time += duration;
+        entry.longest_session = entry.longest_session.max(duration);
+        entry.last_played = entry.last_played.max(session.ended_at);
+
+        match session.mode {
+            SessionMode::Solo => entry.solo += 1,
+            SessionMode::Host => entry.host += 1,
+            SessionMode::Join => entry.join += 1,
+        }
+
+        if session.exit_code.is_some_and(|code| code != 0) {
+            entry.failed += 1;
+        }
+    }
+
+    totals
+}
@@ -0,0 +1,291 @@
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::manifest::DEFAULT_MANIFEST_URL;
+use crate::utils::server::{self, ServerConfig};
+use crate::utils::{appdata, mirrors};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+pub const DEFAULT_ASSET_ENDPOINT: &str = "https://assetdelivery.roblox.com/v1/asset";
+pub const DEFAULT_CACHE_MAX_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Where Wine keeps the Windows environment clients run in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WinePrefix {
+    /// One prefix for every version.
+    #[default]
+    Shared,
+    /// A prefix per version.
+    Version,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Overridden by `FLUSTER_MANIFEST_URL`.
+    pub manifest_url: String,
+    /// Tried in order before the URL a version's manifest entry points at.
+    pub mirrors: Vec<String>,
+    pub server: ServerConfig,
+    pub multicast_address: Ipv4Addr,
+    pub multicast_port: u16,
+    pub user_id: u64,
+    /// Where assets missing from the cache are fetched from.
+    pub asset_endpoint: String,
+    /// Where versions, downloads and everything else Fluster keeps live,
+    /// relative to the directory holding `settings.json` unless absolute.
+    /// `None` keeps them next to it. Overridden by `FLUSTER_DATA_DIR`.
+    pub data_dir: Option<PathBuf>,
+    /// `None` keeps the cache in the Fluster data directory, otherwise it goes
+    /// in a `fluster-cache` directory inside this one.
+    pub cache_dir: Option<PathBuf>,
+    pub cache_max_size: u64,
+    /// Overridden by `FLUSTER_WINE`, found on `PATH` when unset.
+    pub wine_path: Option<PathBuf>,
+    /// Overridden by `FLUSTER_WINE_PREFIX`.
+    pub wine_prefix: WinePrefix,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            manifest_url: DEFAULT_MANIFEST_URL.to_string(),
+            mirrors: Vec::new(),
+            server: ServerConfig::default(),
+            multicast_address: Ipv4Addr::new(239, 255, 42, 17),
+            multicast_port: 58432,
+            user_id: 1,
+            asset_endpoint: DEFAULT_ASSET_ENDPOINT.to_string(),
+            data_dir: None,
+            cache_dir: None,
+            cache_max_size: DEFAULT_CACHE_MAX_SIZE,
+            wine_path: None,
+            wine_prefix: WinePrefix::default(),
+        }
+    }
+}
+
+struct Loaded {
+    settings: Settings,
+    modified: Option<SystemTime>,
+}
+
+static SETTINGS: Mutex<Option<Loaded>> = Mutex::new(None);
+
+fn settings_path() -> Result<PathBuf, String> {
+    Ok(appdata::return_settings_dir()?.join(SETTINGS_FILE))
+}
+
+fn validate_url(name: &str, url: &str) -> Result<(), String> {
+    let parsed =
+        reqwest::Url::parse(url).map_err(|e| format!("The {} {} is not valid: {}", name, url, e))?;
+
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => Err(format!("The {} can't use {} URLs", name, scheme)),
+    }
+}
+
+pub fn validate_settings(settings: &Settings) -> Result<(), String> {
+    validate_url("manifest URL", &settings.manifest_url)?;
+    validate_url("asset endpoint", &settings.asset_endpoint)?;
+
+    for mirror in &settings.mirrors {
+        mirrors::validate_mirror(mirror)?;
+    }
+
+    server::validate_server_config(&settings.server)?;
+
+    if !settings.multicast_address.is_multicast() {
+        return Err(format!(
+            "{} is not a multicast address",
+            settings.multicast_address
+        ));
+    }
+
+    if settings.multicast_port == 0 {
+        return Err("The discovery port must be greater than zero".to_string());
+    }
+
+    if settings.user_id == 0 {
+        return Err("The user id must be greater than zero".to_string());
+    }
+
+    if settings.cache_max_size == 0 {
+        return Err("The cache size limit must be greater than zero".to_string());
+    }
+
+    if settings
+        .cache_dir
+        .as_ref()
+        .is_some_and(|cache_dir| !cache_dir.is_absolute())
+    {
+        return Err("The cache location must be an absolute path".to_string());
+    }
+
+    if settings
+        .data_dir
+        .as_ref()
+        .is_some_and(|data_dir| data_dir.as_os_str().is_empty())
+    {
+        return Err("The data location can't be empty".to_string());
+    }
+
+    if let Some(wine_path) = &settings.wine_path {
+        if !wine_path.is_file() {
+            return Err(format!("Wine was not found at {:?}", wine_path));
+        }
+    }
+
+    Ok(())
+}
+
+fn normalize(settings: &mut Settings) {
+    settings.manifest_url = settings.manifest_url.trim().to_string();
+    settings.asset_endpoint = settings
+        .asset_endpoint
+        .trim()
+        .trim_end_matches(['/', '?'])
+        .to_string();
+
+    settings.mirrors = settings
+        .mirrors
+        .iter()
+        .map(|mirror| mirror.trim().trim_end_matches('/').to_string())
+        .filter(|mirror| !mirror.is_empty())
+        .collect();
+}
+
+fn save(settings: &Settings) -> Result<Option<SystemTime>, String> {
+    let path = settings_path()?;
+
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize the settings: {}", e))?;
+
+    let temp_path = path.with_extension("json.tmp");
+
+    fs::write(&temp_path, content)
+        .and_then(|_| fs::rename(&temp_path, &path))
+        .map_err(|e| format!("Failed to save the settings: {}", e))?;
+
+    Ok(fs::metadata(&path).and_then(|metadata| metadata.modified()).ok())
+}
+
+/// Reads `settings.json`, keeping `previous` when the file was edited into
+/// something that isn't valid.
+fn load(previous: Option<Settings>) -> Loaded {
+    let path = match settings_path() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Loaded {
+                settings: previous.unwrap_or_default(),
+                modified: None,
+            };
+        }
+    };
+
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => {
+            let settings = Settings::default();
+
+            let modified = save(&settings).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                None
+            });
+
+            return Loaded { settings, modified };
+        }
+    };
+
+    let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+
+    let parsed = serde_json::from_str::<Settings>(&content)
+        .map_err(|e| e.to_string())
+        .and_then(|mut settings| {
+            normalize(&mut settings);
+            validate_settings(&settings).map(|_| settings)
+        });
+
+    match parsed {
+        Ok(settings) => Loaded { settings, modified },
+        Err(e) => {
+            eprintln!("Ignoring the invalid settings in {:?}: {}", path, e);
+            Loaded {
+                settings: previous.unwrap_or_default(),
+                modified,
+            }
+        }
+    }
+}
+
+fn file_modified() -> Option<SystemTime> {
+    settings_path()
+        .and_then(|path| fs::metadata(path).map_err(|e| e.to_string()))
+        .and_then(|metadata| metadata.modified().map_err(|e| e.to_string()))
+        .ok()
+}
+
+/// The current settings. Edits made to `settings.json` while Fluster runs
+/// only take effect once [`reload`] applies them.
+pub fn current() -> Settings {
+    let mut loaded = SETTINGS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    loaded
+        .get_or_insert_with(|| load(None))
+        .settings
+        .clone()
+}
+
+/// Whether `settings.json` was edited since it was last read.
+pub fn changed_on_disk() -> bool {
+    let modified = file_modified();
+    let loaded = SETTINGS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    loaded
+        .as_ref()
+        .is_some_and(|loaded| modified.is_some() && loaded.modified != modified)
+}
+
+/// Reads `settings.json` again, returning the settings from before and after.
+pub fn reload() -> (Settings, Settings) {
+    let mut loaded = SETTINGS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let previous = loaded.take().map(|loaded| loaded.settings);
+    let reloaded = load(previous.clone());
+    let settings = reloaded.settings.clone();
+
+    *loaded = Some(reloaded);
+
+    (previous.unwrap_or_default(), settings)
+}
+
+/// Validates and saves a change to the settings, returning them from before
+/// and after it.
+pub fn modify(change: impl FnOnce(&mut Settings)) -> Result<(Settings, Settings), String> {
+    let previous = current();
+
+    let mut settings = previous.clone();
+    change(&mut settings);
+    normalize(&mut settings);
+    validate_settings(&settings)?;
+
+    let mut loaded = SETTINGS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let modified = save(&settings)?;
+
+    *loaded = Some(Loaded {
+        settings: settings.clone(),
+        modified,
+    });
+
+    Ok((previous, settings))
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Bumped whenever the layout of `version_stats.json` changes, together with
+/// a step in `migrate`.
+const STATS_SCHEMA: u32 = 2;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VersionStats {
+    #[serde(default)]
+    pub total_play_time: u64,
+    #[serde(default)]
+    pub last_played: u64,
+    #[serde(default)]
+    pub is_running: bool,
+    #[serde(default)]
+    pub start_time: Option<u64>,
+    #[serde(default)]
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StatsFile {
+    schema: u32,
+    #[serde(default)]
+    versions: HashMap<String, VersionStats>,
+}
+
+impl Default for StatsFile {
+    fn default() -> Self {
+        StatsFile {
+            schema: STATS_SCHEMA,
+            versions: HashMap::new(),
+        }
+    }
+}
+
+struct Stats {
+    path: PathBuf,
+    file: StatsFile,
+}
+
+/// The play statistics of every version, kept in memory and written through
+/// to `version_stats.json` on every change. Lives in Tauri's managed state so
+/// there is a single owner of the file.
+pub struct StatsStore {
+    data: Mutex<Stats>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Brings an older file up to `STATS_SCHEMA`, one schema at a time.
+fn migrate(mut value: Value) -> Value {
+    // files written before the schema was recorded are schema 1
+    let mut schema = value
+        .get("schema")
+        .and_then(Value::as_u64)
+        .unwrap_or(1) as u32;
+
+    while schema < STATS_SCHEMA {
+        // 1 -> 2 only added the schema field itself
+        schema += 1;
+        println!("migrated version stats to schema {}", schema);
+    }
+
+    if let Some(object) = value.as_object_mut() {
+        object.insert("schema".to_string(), Value::from(schema));
+    }
+
+    value
+}
+
+/// Keeps a copy of a stats file that can't be used as is, rather than
+/// silently starting over on top of it.
+fn back_up(path: &Path, reason: &str) {
+    let backup = path.with_extension(format!("json.{}.bak", now()));
+
+    match fs::copy(path, &backup) {
+        Ok(_) => eprintln!("{}, kept a copy at {:?}", reason, backup),
+        Err(e) => eprintln!("{}, and failed to keep a copy: {}", reason, e),
+    }
+}
+
+fn load(path: &Path) -> StatsFile {
+    let Ok(content) = fs::read_to_string(path) else {
+        return StatsFile::default();
+    };
+
+    let value = match serde_json::from_str::<Value>(&content) {
+        Ok(value) => value,
+        Err(e) => {
+            back_up(path, &format!("The version stats are corrupted ({})", e));
+            return StatsFile::default();
+        }
+    };
+
+    let value = migrate(value);
+
+    let mut data = match serde_json::from_value::<StatsFile>(value) {
+        Ok(data) => data,
+        Err(e) => {
+            back_up(path, &format!("The version stats are unreadable ({})", e));
+            return StatsFile::default();
+        }
+    };
+
+    if data.schema > STATS_SCHEMA {
+        back_up(
+            path,
+            "The version stats were written by a newer version of Fluster",
+        );
+        data.schema = STATS_SCHEMA;
+    }
+
+    data
+}
+
+fn save(path: &Path, data: &StatsFile) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(data)
+        .map_err(|e| format!("Failed to serialize the version stats: {}", e))?;
+
+    // written next to the real file and swapped in, so a crash mid-write
+    // leaves the previous stats behind instead of half a file
+    let temp_path = path.with_extension("json.tmp");
+
+    let mut file = File::create(&temp_path)
+        .map_err(|e| format!("Failed to write the version stats: {}", e))?;
+
+    file.write_all(content.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write the version stats: {}", e))?;
+
+    fs::rename(&temp_path, path).map_err(|e| format!("Failed to save the version stats: {}", e))
+}
+
+impl StatsStore {
+    /// Loads the stats at `path`. Nothing can be running before Fluster has
+    /// started, so versions still marked running were left that way by a
+    /// crash and are closed without counting the time nobody watched.
+    pub fn open(path: PathBuf) -> Self {
+        StatsStore {
+            data: Mutex::new(Self::recover(path)),
+        }
+    }
+
+    fn recover(path: PathBuf) -> Stats {
+        let mut data = load(&path);
+
+        let mut recovered = false;
+
+        for (version, stats) in data.versions.iter_mut() {
+            if stats.is_running {
+                println!("recovering {} from a session that never ended", version);
+                stats.is_running = false;
+                stats.start_time = None;
+                recovered = true;
+            }
+        }
+
+        if recovered {
+            save(&path, &data).unwrap_or_else(|e| eprintln!("{}", e));
+        }
+
+        Stats { path, file: data }
+    }
+
+    /// Switches to the stats at `path` after the data directory moved. The
+    /// ones at the old location are left where they are.
+    pub fn relocate(&self, path: PathBuf) {
+        let mut data = self.data();
+
+        if data.path != path {
+            *data = Self::recover(path);
+        }
+    }
+
+    fn data(&self) -> MutexGuard<'_, Stats> {
+        self.data.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn get(&self, version: &str) -> Option<VersionStats> {
+        self.data().file.versions.get(version).cloned()
+    }
+
+    pub fn running_versions(&self) -> Vec<String> {
+        self.data()
+            .file
+            .versions
+            .iter()
+            .filter(|(_, stats)| stats.is_running)
+            .map(|(version, _)| version.clone())
+            .collect()
+    }
+
+    /// Changes the stats of `version` and saves them before anyone else can.
+    pub fn update(&self, version: &str, change: impl FnOnce(&mut VersionStats)) {
+        let mut data = self.data();
+
+        change(data.file.versions.entry(version.to_string()).or_default());
+
+        save(&data.path, &data.file).unwrap_or_else(|e| eprintln!("{}", e));
+    }
+
+    pub fn start_session(&self, version: &str) {
+        let current_time = now();
+
+        self.update(version, |stats| {
+            stats.is_running = true;
+            stats.start_time = Some(current_time);
+            stats.last_played = current_time;
+        });
+    }
+
+    pub fn end_session(&self, version: &str) {
+        let current_time = now();
+
+        self.update(version, |stats| {
+            if !stats.is_running {
+                return;
+            }
+
+            if let Some(start_time) = stats.start_time {
+                stats.total_play_time += current_time.saturating_sub(start_time);
+            }
+
+            stats.is_running = false;
+            stats.start_time = None;
+        });
+    }
+
+    pub fn set_size(&self, version: &str, size_bytes: u64) {
+        self.update(version, |stats| stats.size_bytes = size_bytes);
+    }
+}
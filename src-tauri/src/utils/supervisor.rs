@@ -0,0 +1,183 @@
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// Launches are polled rather than waited on, so they can still be killed
+/// while they are being watched.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long `stop_all` waits for the exits of what it killed to be reported.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LaunchKind {
+    /// A client started on its own from the dashboard.
+    Client,
+    /// A game server started by `start_server`.
+    Server,
+    /// A client joining a server found on the LAN.
+    Join,
+}
+
+/// What a launch was started with, kept for the session history.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LaunchDetails {
+    pub place_file: Option<String>,
+    pub server_address: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Launch {
+    pub id: u64,
+    pub version: String,
+    pub kind: LaunchKind,
+    pub pid: u32,
+    pub started_at: u64,
+    #[serde(flatten)]
+    pub details: LaunchDetails,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchExit {
+    #[serde(flatten)]
+    pub launch: Launch,
+    pub ended_at: u64,
+    /// `None` when the process was killed by a signal or couldn't be waited on.
+    pub exit_code: Option<i32>,
+}
+
+struct Supervised {
+    launch: Launch,
+    child: Arc<Mutex<Child>>,
+    /// Set once `on_exit` has run.
+    reported: Arc<AtomicBool>,
+}
+
+static LAUNCHES: Mutex<Vec<Supervised>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn launches() -> MutexGuard<'static, Vec<Supervised>> {
+    LAUNCHES.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Keeps `child` around until it exits, then hands its exit to `on_exit`.
+pub fn supervise(
+    child: Child,
+    version: &str,
+    kind: LaunchKind,
+    details: LaunchDetails,
+    on_exit: impl FnOnce(LaunchExit) + Send + 'static,
+) -> Launch {
+    let launch = Launch {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        version: version.to_string(),
+        kind,
+        pid: child.id(),
+        started_at: now(),
+        details,
+    };
+
+    let child = Arc::new(Mutex::new(child));
+    let reported = Arc::new(AtomicBool::new(false));
+
+    launches().push(Supervised {
+        launch: launch.clone(),
+        child: child.clone(),
+        reported: reported.clone(),
+    });
+
+    let watched = launch.clone();
+
+    std::thread::spawn(move || {
+        let status = loop {
+            match child.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Failed to wait on process {}: {}", watched.pid, e);
+                    break None;
+                }
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        };
+
+        launches().retain(|supervised| supervised.launch.id != watched.id);
+
+        let exit = LaunchExit {
+            exit_code: status.and_then(|status| status.code()),
+            ended_at: now(),
+            launch: watched,
+        };
+
+        println!(
+            "{:?} {} ({}) exited with {:?}",
+            exit.launch.kind, exit.launch.version, exit.launch.pid, exit.exit_code
+        );
+
+        on_exit(exit);
+        reported.store(true, Ordering::Release);
+    });
+
+    launch
+}
+
+pub fn running() -> Vec<Launch> {
+    launches()
+        .iter()
+        .map(|supervised| supervised.launch.clone())
+        .collect()
+}
+
+/// Whether a client of `version` is still being played, hosted servers aside.
+pub fn is_playing(version: &str) -> bool {
+    launches().iter().any(|supervised| {
+        supervised.launch.version == version && supervised.launch.kind != LaunchKind::Server
+    })
+}
+
+/// Kills every launch of `kind` and waits for their exits to be reported.
+pub fn stop_all(kind: LaunchKind) {
+    let children: Vec<_> = launches()
+        .iter()
+        .filter(|supervised| supervised.launch.kind == kind)
+        .map(|supervised| {
+            (
+                supervised.launch.pid,
+                supervised.child.clone(),
+                supervised.reported.clone(),
+            )
+        })
+        .collect();
+
+    for (pid, child, _) in &children {
+        let mut child = child.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Ok(None) = child.try_wait() {
+            println!("stopping process {}", pid);
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    let started = Instant::now();
+
+    while children
+        .iter()
+        .any(|(_, _, reported)| !reported.load(Ordering::Acquire))
+        && started.elapsed() < REPORT_TIMEOUT
+    {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
@@ -11,8 +11,10 @@ import DiscoveryScreen from "./components/tabs/discovery-screen";
 import { WelcomeScreen } from "./components/screens/WelcomeScreen";
 
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { listen } from "@tauri-apps/api/event";
 
 import { ServerInfo } from "./interfaces/ServerInfo";
+import { ServerStatus } from "./interfaces/ServerStatus";
 import { useVersions } from "./hooks/useVersions";
 import { useView } from "./hooks/useView";
 import dataService from "./services/dataService";
@@ -28,6 +30,7 @@ export default function App() {
     installingVersions,
     installedVersions,
     handleInstall,
+    handleCancelInstall,
     handleUninstall,
   } = useVersions();
 
@@ -50,6 +53,32 @@ export default function App() {
     fetchUserInfo();
   }, []);
 
+  useEffect(() => {
+    const notifyServerFailure = (status: ServerStatus | null) => {
+      if (status?.state === "failed") {
+        showToast("The local web server is not running", {
+          description: status.error ?? "Games will not be able to load.",
+          duration: 10000,
+        });
+      }
+    };
+
+    // the server may have failed before this listener was registered
+    dataService.getServerStatus().then(notifyServerFailure).catch(console.error);
+
+    const unlisten = listen<string>("server_status", (event) => {
+      try {
+        notifyServerFailure(JSON.parse(event.payload) as ServerStatus);
+      } catch (error) {
+        console.error("Failed to parse server status:", error);
+      }
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   const handleMinimize = async () => {
     try {
       await getCurrentWindow().minimize();
@@ -85,8 +114,8 @@ export default function App() {
 
   const handleJoinServer = async (server: ServerInfo, version: string) => {
     try {
-      // this could be dynamic, but for now, we'll assume userId is 1
-      await dataService.launchServerConnection(server, version, 1);
+      const settings = await dataService.getSettings();
+      await dataService.launchServerConnection(server, version, settings.user_id);
     } catch (error) {
       const errorMessage =
         error instanceof Error ? error.message : String(error);
@@ -130,6 +159,7 @@ export default function App() {
             installedVersions={installedVersions}
             isInstalling={isInstalling}
             onInstall={handleInstall}
+            onCancelInstall={handleCancelInstall}
             onUninstall={handleUninstall}
             onGoToDashboard={() => navigateTo("dashboard")}
           />
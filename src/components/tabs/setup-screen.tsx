@@ -1,8 +1,10 @@
 "use client";
 
 import { Button } from "@/components/ui/button";
+import { Progress } from "@/components/ui/progress";
 import { ArrowRight, Download, X, Loader2 } from "lucide-react";
 import { VersionData } from "@/interfaces/VersionData";
+import { InstallProgress } from "@/interfaces/InstallProgress";
 
 interface VersionItemProps {
   version: VersionData;
@@ -17,10 +19,30 @@ interface SetupScreenProps {
   installedVersions: VersionData[];
   isInstalling: boolean;
   onInstall: (id: string) => void;
+  onCancelInstall: (id: string) => void;
   onUninstall: (id: string) => void;
   onGoToDashboard: () => void;
 }
 
+const PHASE_LABELS: Record<InstallProgress["phase"], string> = {
+  downloading: "Downloading",
+  verifying: "Verifying",
+  extracting: "Extracting",
+  finalizing: "Finalizing",
+};
+
+function progressPercent(progress?: InstallProgress): number {
+  if (!progress) return 0;
+
+  if (progress.phase === "extracting" || progress.phase === "finalizing") {
+    return progress.entry_count > 0
+      ? (progress.entry_index / progress.entry_count) * 100
+      : 0;
+  }
+
+  return progress.total ? (progress.downloaded / progress.total) * 100 : 0;
+}
+
 function VersionItem({
   version,
   onClick,
@@ -40,12 +62,33 @@ function VersionItem({
     >
       <div className="flex flex-col flex-grow mr-2">
         <span className="text-base font-medium text-white">{version.name}</span>
-        <span className="text-sm text-white/50">{version.size}</span>
+        {actionType === "installing" && version.progress ? (
+          <div className="mt-2 space-y-1">
+            <Progress value={progressPercent(version.progress)} />
+            <span className="text-xs text-white/50 truncate block">
+              {PHASE_LABELS[version.progress.phase]}
+              {version.progress.entry ? ` ${version.progress.entry}` : ""}
+              {` (${Math.floor(progressPercent(version.progress))}%)`}
+            </span>
+          </div>
+        ) : (
+          <span className="text-sm text-white/50">{version.size}</span>
+        )}
       </div>
 
       {actionType === "installing" ? (
-        <div className="h-8 w-8 rounded-lg bg-white/[0.08] flex items-center justify-center">
-          <Loader2 size={16} className="text-white/50 animate-spin" />
+        <div className="flex items-center gap-2">
+          <div className="h-8 w-8 rounded-lg bg-white/[0.08] flex items-center justify-center">
+            <Loader2 size={16} className="text-white/50 animate-spin" />
+          </div>
+          <Button
+            variant="ghost"
+            size="icon"
+            className="h-8 w-8 rounded-lg bg-white/[0.08] hover:bg-red-500/20 hover:text-red-500"
+            onClick={onClick}
+          >
+            <X size={16} className="text-white/50 group-hover:text-red-500" />
+          </Button>
         </div>
       ) : actionType === "uninstall" ? (
         <Button
@@ -78,6 +121,7 @@ export default function SetupScreen({
   installedVersions,
   isInstalling,
   onInstall,
+  onCancelInstall,
   onUninstall,
   onGoToDashboard,
 }: SetupScreenProps) {
@@ -161,9 +205,8 @@ export default function SetupScreen({
                   <VersionItem
                     key={version.id}
                     version={version}
-                    onClick={() => {}}
+                    onClick={() => onCancelInstall(version.id)}
                     actionType="installing"
-                    disabled
                   />
                 ))}
                 {installedVersions.map((version) => (
@@ -1,5 +1,8 @@
 import { useState, useEffect, useCallback } from 'react';
+import { listen } from '@tauri-apps/api/event';
 import { VersionData } from '../interfaces/VersionData';
+import { InstallProgress } from '../interfaces/InstallProgress';
+import { ClientExit } from '../interfaces/ClientLaunch';
 import dataService from '../services/dataService';
 import { toast } from 'sonner';
 
@@ -24,8 +27,46 @@ export const useVersions = () => {
     refreshVersions().finally(() => setIsLoading(false));
   }, [refreshVersions]);
 
+  useEffect(() => {
+    const unlisten = listen<string>('install_progress', (event) => {
+      try {
+        const progress = JSON.parse(event.payload) as InstallProgress;
+        setVersions(prevVersions =>
+          prevVersions.map(v => (v.id === progress.version ? { ...v, installing: true, progress } : v))
+        );
+      } catch (error) {
+        console.error("Failed to parse install progress:", error);
+      }
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlisten = listen<string>('client_exited', async (event) => {
+      try {
+        const exit = JSON.parse(event.payload) as ClientExit;
+        const stats = await dataService.getVersionStats(exit.version);
+        setVersions(prevVersions =>
+          prevVersions.map(v => (v.id === exit.version ? { ...v, stats } : v))
+        );
+      } catch (error) {
+        console.error("Failed to update the stats of an exited client:", error);
+      }
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
   const handleInstall = useCallback(async (id: string) => {
     setIsInstalling(true);
+    setVersions(prevVersions =>
+      prevVersions.map(v => (v.id === id ? { ...v, installing: true, progress: undefined } : v))
+    );
     try {
       await dataService.installVersion(id);
       await refreshVersions();
@@ -39,11 +80,22 @@ export const useVersions = () => {
         },
       });
       console.error(`Failed to install version ${id}:`, error);
+      setVersions(prevVersions =>
+        prevVersions.map(v => (v.id === id ? { ...v, installing: false, progress: undefined } : v))
+      );
     } finally {
       setIsInstalling(false);
     }
   }, [refreshVersions]);
 
+  const handleCancelInstall = useCallback(async (id: string) => {
+    try {
+      await dataService.cancelInstall(id);
+    } catch (error) {
+      console.error(`Failed to cancel the installation of ${id}:`, error);
+    }
+  }, []);
+
   const handleUninstall = useCallback(async (id: string) => {
     try {
       await dataService.uninstallVersion(id);
@@ -74,6 +126,7 @@ export const useVersions = () => {
     hideInstalledInAvailable,
     setHideInstalledInAvailable,
     handleInstall,
+    handleCancelInstall,
     handleUninstall,
     availableVersions,
     installingVersions,
@@ -0,0 +1,6 @@
+export interface AssetOverride {
+  asset_id: number;
+  file_name: string;
+  version: string | null;
+  size: number;
+}
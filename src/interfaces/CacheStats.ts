@@ -0,0 +1,8 @@
+export interface CacheStats {
+  entries: number;
+  total_size: number;
+  max_size: number;
+  hits: number;
+  misses: number;
+  evictions: number;
+}
@@ -0,0 +1,16 @@
+export type LaunchKind = "client" | "server" | "join";
+
+export interface ClientLaunch {
+  id: number;
+  version: string;
+  kind: LaunchKind;
+  pid: number;
+  started_at: number;
+  place_file: string | null;
+  server_address: string | null;
+}
+
+export interface ClientExit extends ClientLaunch {
+  ended_at: number;
+  exit_code: number | null;
+}
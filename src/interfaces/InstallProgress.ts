@@ -0,0 +1,11 @@
+export type InstallPhase = "downloading" | "verifying" | "extracting" | "finalizing";
+
+export interface InstallProgress {
+  version: string;
+  phase: InstallPhase;
+  downloaded: number;
+  total: number | null;
+  entry: string | null;
+  entry_index: number;
+  entry_count: number;
+}
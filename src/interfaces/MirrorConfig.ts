@@ -0,0 +1,13 @@
+export interface MirrorHealth {
+  successes: number;
+  failures: number;
+  consecutive_failures: number;
+  last_success: number | null;
+  last_failure: number | null;
+  last_error: string | null;
+}
+
+export interface MirrorConfig {
+  mirrors: string[];
+  health: Record<string, MirrorHealth>;
+}
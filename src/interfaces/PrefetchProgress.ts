@@ -0,0 +1,6 @@
+export interface PrefetchProgress {
+  place_file: string;
+  completed: number;
+  total: number;
+  failed: number;
+}
@@ -0,0 +1,25 @@
+export type ServerState = "running" | "stopped" | "failed";
+
+export interface ServerStatus {
+  state: ServerState;
+  address: string;
+  port: number;
+  error: string | null;
+}
+
+export type DomainMode = "hosts" | "loopback";
+
+export interface ServerConfig {
+  address: string;
+  port: number;
+  fallback_port: number;
+  workers: number;
+  domain_mode: DomainMode;
+}
+
+export interface DomainResolution {
+  domain: string;
+  addresses: string[];
+  points_to_local: boolean;
+  error: string | null;
+}
@@ -0,0 +1,22 @@
+export type SessionMode = "solo" | "host" | "join";
+
+export interface Session {
+  version: string;
+  mode: SessionMode;
+  started_at: number;
+  ended_at: number;
+  place_file: string | null;
+  server_address: string | null;
+  exit_code: number | null;
+}
+
+export interface SessionTotals {
+  sessions: number;
+  total_play_time: number;
+  longest_session: number;
+  last_played: number;
+  solo: number;
+  host: number;
+  join: number;
+  failed: number;
+}
@@ -0,0 +1,24 @@
+import { ServerConfig } from "./ServerStatus";
+
+export type WinePrefix = "shared" | "version";
+
+export interface Settings {
+  manifest_url: string;
+  mirrors: string[];
+  server: ServerConfig;
+  multicast_address: string;
+  multicast_port: number;
+  user_id: number;
+  asset_endpoint: string;
+  data_dir: string | null;
+  cache_dir: string | null;
+  cache_max_size: number;
+  wine_path: string | null;
+  wine_prefix: WinePrefix;
+}
+
+export interface DataLocation {
+  settings_dir: string;
+  data_dir: string;
+  portable: boolean;
+}
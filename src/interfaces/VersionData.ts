@@ -1,3 +1,26 @@
+import { InstallProgress } from "./InstallProgress";
+
+export interface ManifestVersion {
+    id: string;
+    name: string;
+    year: number;
+    url: string;
+    size: number;
+    sha256: string;
+    launch_args: string[];
+}
+
+export interface RecordedVersion {
+    id: string;
+    unverified: boolean;
+}
+
+export interface VerifyReport {
+    missing: string[];
+    modified: string[];
+    extra: string[];
+}
+
 export interface VersionStats {
     total_play_time: number;
     last_played: number;
@@ -12,6 +35,7 @@ export interface VersionData {
 
     installed: boolean
     installing: boolean
-    
+    progress?: InstallProgress
+
     stats?: VersionStats
 }
\ No newline at end of file
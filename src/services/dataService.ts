@@ -1,22 +1,55 @@
 import { invoke } from "@tauri-apps/api/core";
 
+import { AssetOverride } from "../interfaces/AssetOverride";
+import { CacheStats } from "../interfaces/CacheStats";
+import { ClientLaunch } from "../interfaces/ClientLaunch";
+import { MirrorConfig } from "../interfaces/MirrorConfig";
 import { ServerInfo } from "../interfaces/ServerInfo";
-import { VersionData, VersionStats } from "../interfaces/VersionData";
+import { Session, SessionTotals } from "../interfaces/Session";
+import { DataLocation, Settings } from "../interfaces/Settings";
+import { DomainResolution, ServerConfig, ServerStatus } from "../interfaces/ServerStatus";
+import { ManifestVersion, RecordedVersion, VerifyReport, VersionData, VersionStats } from "../interfaces/VersionData";
 
 const dataService = {
   getAvailableVersions: async (): Promise<VersionData[]> => {
-    const versions: VersionData[] = [
-      {
-        id: "version-997deaae24a8",
-        name: "Roblox 2008E",
+    let manifest: ManifestVersion[] = [];
+
+    try {
+      const manifestJson = await invoke<string>("list_available_versions");
+      manifest = JSON.parse(manifestJson) as ManifestVersion[];
+    } catch (error) {
+      // installed builds can still be played without the manifest
+      console.error("Failed to fetch the version manifest:", error);
+    }
+
+    const installedJson = await invoke<string>("list_installed_versions");
+    const installed = JSON.parse(installedJson) as RecordedVersion[];
+
+    // imported custom builds have no manifest entry to list them by
+    const entries = [
+      ...manifest.map((entry) => ({ id: entry.id, name: entry.name, type: undefined })),
+      ...installed
+        .filter((recorded) => !manifest.some((entry) => entry.id === recorded.id))
+        .map((recorded) => ({
+          id: recorded.id,
+          name: recorded.id,
+          type: recorded.unverified ? "Unverified custom build" : undefined,
+        })),
+    ];
+
+    const versions: VersionData[] = await Promise.all(
+      entries.map(async (entry) => ({
+        id: entry.id,
+        name: entry.name,
+        type: entry.type,
         size: await invoke<string>("get_version_size", {
-          version: "version-997deaae24a8",
+          version: entry.id,
         }),
-        installed: await dataService.versionInstalled("version-997deaae24a8"),
+        installed: await dataService.versionInstalled(entry.id),
         installing: false,
         stats: undefined,
-      },
-    ];
+      })),
+    );
 
     for (const version of versions) {
       try {
@@ -43,6 +76,108 @@ const dataService = {
     return await invoke("install_client", { version });
   },
 
+  cancelInstall: async (version: string): Promise<boolean> => {
+    return await invoke("cancel_install", { version });
+  },
+
+  importVersion: async (
+    version: string,
+    path: string,
+    allowUnverified: boolean
+  ): Promise<boolean> => {
+    return await invoke("import_client", { version, path, allowUnverified });
+  },
+
+  verifyVersion: async (version: string): Promise<VerifyReport> => {
+    const reportJson = await invoke<string>("verify_client", { version });
+    return JSON.parse(reportJson) as VerifyReport;
+  },
+
+  repairVersion: async (version: string): Promise<string[]> => {
+    const repairedJson = await invoke<string>("repair_client", { version });
+    return JSON.parse(repairedJson) as string[];
+  },
+
+  getMirrors: async (): Promise<MirrorConfig> => {
+    const mirrorsJson = await invoke<string>("get_mirrors");
+    return JSON.parse(mirrorsJson) as MirrorConfig;
+  },
+
+  setMirrors: async (mirrors: string[]): Promise<void> => {
+    return await invoke("set_mirrors", { mirrors });
+  },
+
+  getCacheStats: async (): Promise<CacheStats> => {
+    const statsJson = await invoke<string>("get_cache_stats");
+    return JSON.parse(statsJson) as CacheStats;
+  },
+
+  clearCache: async (): Promise<number> => {
+    return await invoke("clear_cache");
+  },
+
+  setCacheMaxSize: async (maxSize: number): Promise<void> => {
+    return await invoke("set_cache_max_size", { maxSize });
+  },
+
+  exportAssetPack: async (
+    path: string,
+    assetIds?: number[],
+    placePath?: string
+  ): Promise<number> => {
+    return await invoke("export_asset_pack", { path, assetIds, placePath });
+  },
+
+  importAssetPack: async (path: string): Promise<number> => {
+    return await invoke("import_asset_pack", { path });
+  },
+
+  getOverrides: async (version?: string): Promise<AssetOverride[]> => {
+    const overridesJson = await invoke<string>("list_overrides", { version });
+    return JSON.parse(overridesJson) as AssetOverride[];
+  },
+
+  addOverride: async (
+    assetId: number,
+    path: string,
+    version?: string
+  ): Promise<void> => {
+    return await invoke("add_override", { assetId, path, version });
+  },
+
+  removeOverride: async (assetId: number, version?: string): Promise<boolean> => {
+    return await invoke("remove_override", { assetId, version });
+  },
+
+  getServerStatus: async (): Promise<ServerStatus | null> => {
+    const statusJson = await invoke<string>("get_server_status");
+    return JSON.parse(statusJson) as ServerStatus | null;
+  },
+
+  getServerConfig: async (): Promise<ServerConfig> => {
+    const configJson = await invoke<string>("get_server_config");
+    return JSON.parse(configJson) as ServerConfig;
+  },
+
+  setServerConfig: async (config: ServerConfig): Promise<void> => {
+    return await invoke("set_server_config", { config });
+  },
+
+  getSettings: async (): Promise<Settings> => {
+    const settingsJson = await invoke<string>("get_settings");
+    return JSON.parse(settingsJson) as Settings;
+  },
+
+  updateSettings: async (settings: Settings): Promise<Settings> => {
+    const settingsJson = await invoke<string>("update_settings", { settings });
+    return JSON.parse(settingsJson) as Settings;
+  },
+
+  getDataLocation: async (): Promise<DataLocation> => {
+    const locationJson = await invoke<string>("get_data_location");
+    return JSON.parse(locationJson) as DataLocation;
+  },
+
   uninstallVersion: async (version: string): Promise<string> => {
     return await invoke("uninstall_client", { version });
   },
@@ -55,6 +190,21 @@ const dataService = {
     return await invoke("launch_client", { version });
   },
 
+  getRunningClients: async (): Promise<ClientLaunch[]> => {
+    const launchesJson = await invoke<string>("get_running_clients");
+    return JSON.parse(launchesJson) as ClientLaunch[];
+  },
+
+  getSessionHistory: async (version?: string, limit?: number): Promise<Session[]> => {
+    const historyJson = await invoke<string>("get_session_history", { version, limit });
+    return JSON.parse(historyJson) as Session[];
+  },
+
+  getSessionTotals: async (): Promise<Record<string, SessionTotals>> => {
+    const totalsJson = await invoke<string>("get_session_totals");
+    return JSON.parse(totalsJson) as Record<string, SessionTotals>;
+  },
+
   getVersionStats: async (version: string): Promise<VersionStats> => {
     const statsJson = await invoke<string>("get_version_stats", { version });
     return JSON.parse(statsJson) as VersionStats;
@@ -72,6 +222,15 @@ const dataService = {
     return await invoke("setup_hosts_file");
   },
 
+  uninstallHostsEntries: async (): Promise<string> => {
+    return await invoke<string>("uninstall_hosts_entries");
+  },
+
+  checkDomainResolution: async (): Promise<DomainResolution> => {
+    const resolutionJson = await invoke<string>("check_domain_resolution");
+    return JSON.parse(resolutionJson) as DomainResolution;
+  },
+
   flusterSetup: async (): Promise<void> => {
     return await invoke("fluster_setup");
   },